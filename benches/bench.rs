@@ -1,18 +1,24 @@
+// the lint policy of the library, see src/lib.rs
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 #[macro_use]
 extern crate criterion;
 
 use criterion::Criterion;
-use ff::Field;
+use ff::{Field, PrimeField};
 use ole::fft::{
-    digit_reverse_swap, fft2, fft2_in_place, fft2_inverse, fft3, fft3_in_place, fft3_inverse,
+    digit_reverse_swap, fft2, fft2_batch, fft2_in_place, fft2_inverse, fft3, fft3_batch,
+    fft3_in_place, fft3_inverse, EvaluationDomain,
 };
-use ole::field::{Fp, OleField};
+use ole::field::{BatchField, FiniteField, Fp, OleField};
 use ole::ole::{OleReceiver, OleSender, Receiver, Sender};
-use ole::poly::{euclid_division, lagrangian_interpolation, poly_from_roots};
+use ole::poly::{
+    euclid_division, fast_euclid_division, fast_euclid_division_with, lagrangian_interpolation,
+    poly_from_roots, poly_mul, MulDomains,
+};
 use ole::shamir::{reconstruct, share};
-use rand;
 use rand::seq::IteratorRandom;
-use scuttlebutt::Channel;
+use scuttlebutt::{Block, Channel};
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -66,31 +72,170 @@ pub fn bench_fft3_inverse(c: &mut Criterion) {
     });
 }
 
+pub fn bench_fft2_batch(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<Fp>> = (0..8)
+        .map(|_| (0..Fp::A).map(|_| Fp::random(&mut rng)).collect())
+        .collect();
+
+    let mut batch = vectors.clone();
+    c.bench_function(&format!("fft2_batch, 8 x {} points", Fp::A), move |b| {
+        b.iter(|| fft2_batch(&mut batch, &Fp::alpha()))
+    });
+
+    let mut batch = vectors;
+    c.bench_function(
+        &format!("fft2_in_place one by one, 8 x {} points", Fp::A),
+        move |b| {
+            b.iter(|| {
+                for v in batch.iter_mut() {
+                    fft2_in_place(v, &Fp::alpha());
+                }
+            })
+        },
+    );
+}
+
+pub fn bench_fft3_batch(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<Fp>> = (0..8)
+        .map(|_| (0..Fp::B).map(|_| Fp::random(&mut rng)).collect())
+        .collect();
+
+    let mut batch = vectors.clone();
+    c.bench_function(&format!("fft3_batch, 8 x {} points", Fp::B), move |b| {
+        b.iter(|| fft3_batch(&mut batch, &Fp::beta()))
+    });
+
+    let mut batch = vectors;
+    c.bench_function(
+        &format!("fft3_in_place one by one, 8 x {} points", Fp::B),
+        move |b| {
+            b.iter(|| {
+                for v in batch.iter_mut() {
+                    fft3_in_place(v, &Fp::beta());
+                }
+            })
+        },
+    );
+}
+
+// Prime q = 4611672963082616833, with bit size 62
+// q = 2^20 * 3^13 * 541 * 5099 + 1, for the large FFTs
+// generator of multiplicative group: 5
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "4611672963082616833"]
+#[PrimeFieldGenerator = "5"]
+pub struct FpLarge(FpLargeRepr);
+
+impl BatchField for FpLarge {}
+
+pub fn bench_fft_large(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    for (n, radix) in [(1 << 20, 2), (3usize.pow(13), 3)].iter() {
+        let omega = FpLarge::subgroup_generator(*n as u64).unwrap();
+        let coeffs: Vec<FpLarge> = (0..*n).map(|_| FpLarge::random(&mut rng)).collect();
+
+        let domain = EvaluationDomain::new(*n, omega);
+        let mut values = coeffs.to_vec();
+        c.bench_function(&format!("fft, {} points, six-step", n), move |b| {
+            b.iter(|| domain.fft(&mut values))
+        });
+
+        // the in place FFTs switch to six-step at these sizes, and build it each time
+        let mut values = coeffs.to_vec();
+        let radix = *radix;
+        c.bench_function(
+            &format!("fft{}_in_place, {} points, six-step", radix, n),
+            move |b| {
+                b.iter(|| {
+                    if radix == 2 {
+                        fft2_in_place(&mut values, &omega)
+                    } else {
+                        fft3_in_place(&mut values, &omega)
+                    }
+                })
+            },
+        );
+    }
+}
+
+// The encoding of an OLE input: interpolating Fp::A / 2 points on the even powers of
+// alpha, then evaluating at the first code length powers of beta
+pub fn bench_encoding(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let n = Fp::A / 2;
+    let points: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+    let input_domain = EvaluationDomain::new(n, Fp::alpha().pow([2]));
+    let beta_domain = EvaluationDomain::new(Fp::B, Fp::beta());
+    let mut poly = points.to_vec();
+    input_domain.fft_inverse(&mut poly);
+
+    c.bench_function(&format!("encoding interpolation, {} points", n), move |b| {
+        b.iter(|| {
+            let mut poly = points.to_vec();
+            input_domain.fft_inverse(&mut poly);
+            poly
+        })
+    });
+
+    let poly_copy = poly.to_vec();
+    let domain = beta_domain.clone();
+    c.bench_function(
+        &format!("encoding fft, {} coefficients, {} points", n, Fp::B),
+        move |b| {
+            b.iter(|| {
+                let mut values = poly_copy.to_vec();
+                values.resize_with(Fp::B, Fp::zero);
+                domain.fft(&mut values);
+                values
+            })
+        },
+    );
+
+    let poly_copy = poly.to_vec();
+    let domain = beta_domain.clone();
+    c.bench_function(
+        &format!("encoding fft_pruned, {} coefficients, {} points", n, Fp::B),
+        move |b| b.iter(|| domain.fft_pruned(&poly_copy, Fp::B)),
+    );
+
+    // the code length of 256 + 128 noisy positions
+    let outputs = Fp::A + 128;
+    c.bench_function(
+        &format!(
+            "encoding fft_pruned, {} coefficients, {} points",
+            n, outputs
+        ),
+        move |b| b.iter(|| beta_domain.fft_pruned(&poly, outputs)),
+    );
+}
+
 pub fn bench_digit_reverse_swap(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
 
-    let n = 2usize.pow(11 as u32);
+    let n = 2usize.pow(11);
     let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("digit_reverse_swap, size {}, base 2", n),
         move |b| b.iter(|| digit_reverse_swap(&mut data, 2)),
     );
 
-    let n = 2usize.pow(22 as u32);
+    let n = 2usize.pow(22);
     let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("digit_reverse_swap, size {}, base 2", n),
         move |b| b.iter(|| digit_reverse_swap(&mut data, 2)),
     );
 
-    let n = 3usize.pow(10 as u32);
+    let n = 3usize.pow(10);
     let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("digit_reverse_swap, size {}, base 3", n),
         move |b| b.iter(|| digit_reverse_swap(&mut data, 3)),
     );
 
-    let n = 3usize.pow(15 as u32);
+    let n = 3usize.pow(15);
     let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("digit_reverse_swap, size {}, base 3", n),
@@ -102,40 +247,94 @@ pub fn bench_poly_from_roots(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
 
     let n = 10000;
-    let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+    let data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(&format!("poly_from_roots1, size {}", n), move |b| {
-        b.iter(|| poly_from_roots(&mut data))
+        b.iter(|| poly_from_roots(&data))
     });
 
     let n = 3usize.pow(7) - 2usize.pow(8);
-    let mut data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+    let data: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(&format!("poly_from_roots2, size {}", n), move |b| {
-        b.iter(|| poly_from_roots(&mut data))
+        b.iter(|| poly_from_roots(&data))
     });
 }
 
 pub fn bench_euclid_division(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
 
-    let na = 3usize.pow(9 as u32);
-    let nb = 2usize.pow(11 as u32);
-    let mut a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
+    let na = 3usize.pow(9);
+    let nb = 2usize.pow(11);
+    let a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
     let b: Vec<Fp> = (0..nb).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("euclid_division, size a = {}, size b = {}", na, nb),
-        move |b_| b_.iter(|| euclid_division(&mut a, &b)),
+        move |b_| b_.iter(|| euclid_division(&a, &b)),
     );
 
     let na = Fp::B;
     let nb = Fp::A;
-    let mut a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
+    let a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
     let b: Vec<Fp> = (0..nb).map(|_| Fp::random(&mut rng)).collect();
     c.bench_function(
         &format!("euclid_division, size a = {}, size b = {}", na, nb),
-        move |b_| b_.iter(|| euclid_division(&mut a, &b)),
+        move |b_| b_.iter(|| euclid_division(&a, &b)),
     );
 }
 
+pub fn bench_fast_euclid_division(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    let na = 3usize.pow(9);
+    let nb = 2usize.pow(11);
+    let a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
+    let b: Vec<Fp> = (0..nb).map(|_| Fp::random(&mut rng)).collect();
+    c.bench_function(
+        &format!("fast_euclid_division, size a = {}, size b = {}", na, nb),
+        move |b_| b_.iter(|| fast_euclid_division(&a, &b)),
+    );
+
+    let na = Fp::B;
+    let nb = Fp::A;
+    let a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
+    let b: Vec<Fp> = (0..nb).map(|_| Fp::random(&mut rng)).collect();
+    c.bench_function(
+        &format!("fast_euclid_division, size a = {}, size b = {}", na, nb),
+        move |b_| b_.iter(|| fast_euclid_division(&a, &b)),
+    );
+
+    // the division of decode_reed_solomon and shamir::reconstruct, which take the FFT
+    // domains from the OLE parameters
+    let na = Fp::B;
+    let nb = Fp::B - Fp::A + 1;
+    let a: Vec<Fp> = (0..na).map(|_| Fp::random(&mut rng)).collect();
+    let b: Vec<Fp> = (0..nb).map(|_| Fp::random(&mut rng)).collect();
+    let (a_copy, b_copy) = (a.to_vec(), b.to_vec());
+    c.bench_function(
+        &format!("fast_euclid_division, size a = {}, size b = {}", na, nb),
+        move |b_| b_.iter(|| fast_euclid_division(&a_copy, &b_copy)),
+    );
+    let domains = MulDomains::new();
+    c.bench_function(
+        &format!(
+            "fast_euclid_division, size a = {}, size b = {}, cached domains",
+            na, nb
+        ),
+        move |b_| b_.iter(|| fast_euclid_division_with(&a, &b, &domains)),
+    );
+}
+
+pub fn bench_poly_mul(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    for n in [100usize, 2000, 100000].iter() {
+        let a: Vec<Fp> = (0..*n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..*n).map(|_| Fp::random(&mut rng)).collect();
+        c.bench_function(&format!("poly_mul, size {}", n), move |b_| {
+            b_.iter(|| poly_mul(&a, &b))
+        });
+    }
+}
+
 pub fn bench_lagrange(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
     let n = Fp::B - Fp::A;
@@ -153,10 +352,10 @@ pub fn bench_share(c: &mut Criterion) {
     let secret = Fp::random(&mut rng);
     let n = Fp::B;
     let rho = Fp::B - Fp::A;
-    let omega = Fp::beta();
+    let domain = EvaluationDomain::new(n, Fp::beta());
 
     c.bench_function(&format!("share, n = {}, rho = {}", n, rho), move |b_| {
-        b_.iter(|| share(&secret, n as u64, rho as u64, &omega))
+        b_.iter(|| share(&secret, rho as u64, &domain, &mut rng))
     });
 }
 
@@ -165,16 +364,16 @@ pub fn bench_reconstruct(c: &mut Criterion) {
     let secret = Fp::random(&mut rng);
     let n = Fp::B;
     let rho = Fp::B - Fp::A;
-    let omega = Fp::beta();
+    let domain = EvaluationDomain::new(n, Fp::beta());
 
-    let shares = share(&secret, n as u64, rho as u64, &omega);
+    let shares = share(&secret, rho as u64, &domain, &mut rng);
     let mut indices: Vec<usize> = (0..n).choose_multiple(&mut rng, rho);
     indices.sort();
     let myshares: Vec<Fp> = indices.iter().map(|i| shares[*i]).collect();
 
     c.bench_function(
         &format!("reconstruct, n = {}, rho = {}", n, rho),
-        move |b_| b_.iter(|| reconstruct(&indices, &myshares, n as u64, rho as u64, &omega)),
+        move |b_| b_.iter(|| reconstruct(&indices, &myshares, rho as u64, &domain)),
     );
 }
 
@@ -257,31 +456,151 @@ pub fn bench_ole_init(c: &mut Criterion) {
     });
 }
 
-criterion_group!(bench_ole, bench_ole_send_receive, bench_ole_init);
+pub fn bench_ole_restore(c: &mut Criterion) {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    let session_id = Block::default();
+    let handle = std::thread::spawn(move || {
+        let mut rng = rand::thread_rng();
+        let reader = BufReader::new(sender.try_clone().unwrap());
+        let writer = BufWriter::new(sender);
+        let mut sender_channel = Channel::new(reader, writer);
+        let mut olesender = OleSender::init(&mut sender_channel, &mut rng).unwrap();
+        olesender
+            .precompute::<Fp, _, _>(Fp::A / 2, &mut sender_channel, &mut rng)
+            .unwrap();
+        olesender.save_pool(&session_id)
+    });
+    let mut rng = rand::thread_rng();
+    let reader = BufReader::new(receiver.try_clone().unwrap());
+    let writer = BufWriter::new(receiver);
+    let mut receiver_channel = Channel::new(reader, writer);
+    let mut olereceiver = OleReceiver::init(&mut receiver_channel, &mut rng).unwrap();
+    olereceiver
+        .precompute::<Fp, _, _>(Fp::A / 2, &mut receiver_channel, &mut rng)
+        .unwrap();
+    let receiver_state = olereceiver.save_pool(&session_id);
+    let sender_state = handle.join().unwrap();
+
+    // every iteration restores fresh copies of the same state, which reuses the OTs and
+    // is only fine as no input is run
+    c.bench_function("ole restore", move |b_| {
+        b_.iter(|| {
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let mut sender_state = sender_state.clone();
+            let mut receiver_state = receiver_state.clone();
+            let handle = std::thread::spawn(move || {
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                let mut sender_channel = Channel::new(reader, writer);
+                let _olesender =
+                    OleSender::restore_pool(&mut sender_state, &mut sender_channel).unwrap();
+            });
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            let mut receiver_channel = Channel::new(reader, writer);
+            let _olereceiver =
+                OleReceiver::restore_pool(&mut receiver_state, &mut receiver_channel).unwrap();
+            handle.join().unwrap();
+        })
+    });
+}
+
+// The generic loops are what every field gets from the BatchField defaults, the batch
+// functions take the Fp fast path.
+pub fn bench_batch_mul(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut a: Vec<Fp> = (0..1024).map(|_| Fp::random(&mut rng)).collect();
+    let b: Vec<Fp> = (0..1024).map(|_| Fp::random(&mut rng)).collect();
+    let mut a_copy = a.to_vec();
+    let b_copy = b.to_vec();
+    c.bench_function("generic mul_assign loop, 1024 elements", move |b_| {
+        b_.iter(|| {
+            for (x, y) in a_copy.iter_mut().zip(&b_copy) {
+                x.mul_assign(y);
+            }
+        })
+    });
+    c.bench_function("batch_mul, 1024 elements", move |b_| {
+        b_.iter(|| Fp::batch_mul(&mut a, &b))
+    });
+}
+
+pub fn bench_batch_inverse(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let a: Vec<Fp> = (0..1024).map(|_| Fp::random(&mut rng)).collect();
+    let a_copy = a.to_vec();
+    c.bench_function("generic Montgomery trick, 1024 elements", move |b_| {
+        b_.iter(|| {
+            let mut prefix = Vec::with_capacity(a_copy.len());
+            let mut acc = Fp::one();
+            for x in a_copy.iter() {
+                prefix.push(acc);
+                acc.mul_assign(x);
+            }
+            let mut inv = acc.inverse().unwrap();
+            let mut result = a_copy.to_vec();
+            for (x, p) in result.iter_mut().zip(prefix).rev() {
+                let mut x_inv = inv;
+                x_inv.mul_assign(&p);
+                inv.mul_assign(x);
+                *x = x_inv;
+            }
+            result
+        })
+    });
+    c.bench_function("batch_inverse, 1024 elements", move |b_| {
+        b_.iter(|| {
+            let mut inv = a.to_vec();
+            Fp::batch_inverse(&mut inv).unwrap();
+            inv
+        })
+    });
+}
+
+criterion_group!(
+    bench_ole,
+    bench_ole_send_receive,
+    bench_ole_init,
+    bench_ole_restore
+);
 criterion_group!(bench_ss, bench_share, bench_reconstruct);
 criterion_group!(
     bench_poly,
     bench_poly_from_roots,
     bench_euclid_division,
+    bench_fast_euclid_division,
+    bench_poly_mul,
     bench_lagrange
 );
 criterion_group!(
     bench_fft2,
     bench_fft2_in_place,
     bench_fft2_inverse,
-    bench_fft2_out_of_place
+    bench_fft2_out_of_place,
+    bench_fft2_batch
 );
 criterion_group!(
     bench_fft3,
     bench_fft3_in_place,
     bench_fft3_inverse,
-    bench_fft3_out_of_place
+    bench_fft3_out_of_place,
+    bench_fft3_batch
 );
 criterion_group!(bench_digit_reverse, bench_digit_reverse_swap);
+criterion_group!(bench_encode, bench_encoding);
+criterion_group! {
+    name = bench_large;
+    config = Criterion::default().sample_size(10);
+    targets = bench_fft_large
+}
+criterion_group!(bench_batch, bench_batch_mul, bench_batch_inverse);
 criterion_main!(
     bench_fft2,
     bench_fft3,
     bench_digit_reverse,
+    bench_encode,
+    bench_large,
+    bench_batch,
     bench_poly,
     bench_ss,
     bench_ole
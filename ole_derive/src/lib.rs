@@ -0,0 +1,165 @@
+// explicit returns are the style of the ole crates
+#![allow(clippy::needless_return)]
+
+extern crate proc_macro;
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+use quote::quote;
+
+// Derives ole::field::OleField for a field derived with ff's PrimeField. The impl names
+// the trait by its full path ::ole::field::OleField, so it needn't be in scope. Fields
+// defined in the ole crate itself set #[OleFieldCrate = "crate"] to name it from there.
+//
+// Reads the PrimeFieldModulus and PrimeFieldGenerator attributes of the PrimeField
+// derive, and the orders of the alpha and beta subgroups:
+//
+//     #[derive(PrimeField, OleField)]
+//     #[PrimeFieldModulus = "..."]
+//     #[PrimeFieldGenerator = "5"]
+//     #[OleFieldAlphaOrder = "256"]
+//     #[OleFieldBetaOrder = "2187"]
+//     pub struct Fp(FpRepr);
+//
+// alpha() and beta() are g^((p-1)/A) and g^((p-1)/B) for the generator g, computed at
+// build time. Fails to compile unless A is a power of 2 and B a power of 3, both
+// dividing p-1, and g gives elements of exactly those orders.
+#[proc_macro_derive(
+    OleField,
+    attributes(OleFieldAlphaOrder, OleFieldBetaOrder, OleFieldCrate)
+)]
+pub fn ole_field(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast: syn::DeriveInput = syn::parse(input).unwrap();
+    match ole_field_impl(&ast) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn ole_field_impl(ast: &syn::DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let name = &ast.ident;
+    let repr = fetch_wrapped_ident(ast)?;
+
+    let modulus: BigUint = parse_attr(ast, "PrimeFieldModulus")?;
+    let generator: BigUint = parse_attr(ast, "PrimeFieldGenerator")?;
+    let alpha_order: u64 = parse_attr(ast, "OleFieldAlphaOrder")?;
+    let beta_order: u64 = parse_attr(ast, "OleFieldBetaOrder")?;
+    let krate: syn::Path = match parse_attr::<String>(ast, "OleFieldCrate") {
+        Ok(path) => syn::parse_str(&path).map_err(|_| error(ast, "invalid OleFieldCrate"))?,
+        Err(_) => syn::parse_quote!(::ole),
+    };
+
+    if alpha_order < 2 || !alpha_order.is_power_of_two() {
+        return Err(error(
+            ast,
+            "OleFieldAlphaOrder must be a power of 2 of at least 2",
+        ));
+    }
+    if !is_power_of_three(beta_order) {
+        return Err(error(
+            ast,
+            "OleFieldBetaOrder must be a power of 3 of at least 3",
+        ));
+    }
+    let alpha = root_of_unity(&modulus, &generator, alpha_order, 2)
+        .map_err(|e| error(ast, &format!("OleFieldAlphaOrder {}", e)))?;
+    let beta = root_of_unity(&modulus, &generator, beta_order, 3)
+        .map_err(|e| error(ast, &format!("OleFieldBetaOrder {}", e)))?;
+
+    // PrimeField keeps elements in Montgomery form x*R mod p, with R = 2^(64*limbs) for
+    // the least number of limbs such that 2p < R
+    let mut limbs = 1;
+    while (BigUint::one() << (64 * limbs)) < (&modulus << 1) {
+        limbs += 1;
+    }
+    let r = (BigUint::one() << (64 * limbs)) % &modulus;
+    let alpha_limbs = to_limbs(&((alpha * &r) % &modulus), limbs);
+    let beta_limbs = to_limbs(&((beta * &r) % &modulus), limbs);
+    let a = alpha_order as usize;
+    let b = beta_order as usize;
+
+    Ok(quote! {
+        impl #krate::field::OleField for #name {
+            const A: usize = #a;
+            const B: usize = #b;
+
+            fn alpha() -> Self {
+                #name(#repr([#(#alpha_limbs,)*]))
+            }
+
+            fn beta() -> Self {
+                #name(#repr([#(#beta_limbs,)*]))
+            }
+        }
+    })
+}
+
+// the type wrapped by the struct, i.e. FpRepr in struct Fp(FpRepr)
+fn fetch_wrapped_ident(ast: &syn::DeriveInput) -> Result<syn::Ident, syn::Error> {
+    if let syn::Data::Struct(ref s) = ast.data {
+        if let syn::Fields::Unnamed(ref fields) = s.fields {
+            if fields.unnamed.len() == 1 {
+                if let syn::Type::Path(ref path) = fields.unnamed[0].ty {
+                    if let Some(ident) = path.path.get_ident() {
+                        return Ok(ident.clone());
+                    }
+                }
+            }
+        }
+    }
+    Err(error(
+        ast,
+        "OleField can only be derived for a struct wrapping its repr",
+    ))
+}
+
+// the value of the attribute #[name = "..."]
+fn parse_attr<T: std::str::FromStr>(ast: &syn::DeriveInput, name: &str) -> Result<T, syn::Error> {
+    for attr in ast.attrs.iter() {
+        if let Ok(syn::Meta::NameValue(nv)) = attr.parse_meta() {
+            if nv.path.is_ident(name) {
+                if let syn::Lit::Str(s) = nv.lit {
+                    return s
+                        .value()
+                        .parse()
+                        .map_err(|_| syn::Error::new_spanned(s, "expected an integer"));
+                }
+            }
+        }
+    }
+    Err(error(ast, &format!("missing attribute {}", name)))
+}
+
+fn error(ast: &syn::DeriveInput, msg: &str) -> syn::Error {
+    syn::Error::new_spanned(&ast.ident, msg)
+}
+
+fn is_power_of_three(mut n: u64) -> bool {
+    if n < 3 {
+        return false;
+    }
+    while n.is_multiple_of(3) {
+        n /= 3;
+    }
+    return n == 1;
+}
+
+// g^((p-1)/n), which must have order exactly n, a power of the prime factor
+fn root_of_unity(p: &BigUint, g: &BigUint, n: u64, factor: u64) -> Result<BigUint, &'static str> {
+    let p_minus_one = p - BigUint::one();
+    if !(&p_minus_one % n).is_zero() {
+        return Err("does not divide p-1");
+    }
+    let root = g.modpow(&(&p_minus_one / n), p);
+    if root.modpow(&BigUint::from(n / factor), p).is_one() {
+        return Err("is not the order of any power of PrimeFieldGenerator");
+    }
+    return Ok(root);
+}
+
+fn to_limbs(x: &BigUint, limbs: usize) -> Vec<u64> {
+    let mask = BigUint::from(u64::MAX);
+    (0..limbs)
+        .map(|i| ((x >> (64 * i)) & &mask).to_u64().unwrap())
+        .collect()
+}
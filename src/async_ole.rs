@@ -0,0 +1,924 @@
+use crate::error::OleError;
+use crate::field::OleField;
+use crate::ole::{
+    num_ots, to_bytes, to_elements, OleReceiver, OleSender, RandomOleReceiver, RandomOleSender,
+    Receiver, ReceiverRound, Sender, SenderRound, ROUND_INSTANCES,
+};
+use crate::ot_pool;
+use crate::params::OleParams;
+use async_trait::async_trait;
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{channel::AbstractChannel, AesRng, Block};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io,
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc as async_mpsc,
+};
+
+// Async counterparts of ole::Sender and ole::Receiver, running the same protocol over an
+// AsyncChannel. They drive the same rounds as the sync parties, ole::SenderRound and
+// ole::ReceiverRound, and send the same messages, so an async party can run OLE with a
+// sync one, random OLE and saved pools included.
+//
+// The OT-extension of ocelot only works on a blocking AbstractChannel, and its KOS
+// message flow is internal to ocelot's blocking Sender and Receiver, so running it on
+// the async task would mean reimplementing KOS here. Instead the OTs don't run on the
+// async task: the OTs of each round of ROUND_INSTANCES instances, and the base OTs of
+// init and precompute, run as one job on tokio's blocking thread pool, talking to the
+// protocol through in-memory queues which are forwarded to the async transport. Each
+// running session holds a blocking thread for the duration of its OTs, so the number of
+// sessions doing OTs at once is bounded by the size of the blocking pool, and the
+// runtime must allow blocking tasks. Everything else is done on the async task.
+
+#[async_trait]
+pub trait AsyncSender
+where
+    Self: Sized + Send,
+{
+    // builds a pool of OTs via OT-extension, to be used for OLE
+    async fn init<S, Crng>(channel: &mut AsyncChannel<S>, rng: &mut Crng) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send;
+
+    // runs OLE with the default parameters of the field
+    async fn input<F, S, Crng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        self.input_with_params(a, b, &OleParams::default(), channel, rng)
+            .await
+    }
+
+    // the receiver must use the same parameters
+    async fn input_with_params<F, S, Crng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send;
+
+    // offline phase: runs n OLEs on random inputs a, b, to be derandomized later by
+    // RandomOleSender::input_async once the actual inputs are known
+    async fn input_random<F, S, Crng>(
+        &mut self,
+        n: usize,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<RandomOleSender<F>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let a: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        self.input(&a, &b, channel, rng).await?;
+        Ok(RandomOleSender::new(a, b))
+    }
+}
+
+#[async_trait]
+pub trait AsyncReceiver
+where
+    Self: Sized + Send,
+{
+    // builds a pool of OTs via OT-extension, to be used for OLE
+    async fn init<S, Crng>(channel: &mut AsyncChannel<S>, rng: &mut Crng) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send;
+
+    // runs OLE with the default parameters of the field
+    async fn input<F, S, Crng>(
+        &mut self,
+        x: &[F],
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        self.input_with_params(x, &OleParams::default(), channel, rng)
+            .await
+    }
+
+    // the sender must use the same parameters
+    async fn input_with_params<F, S, Crng>(
+        &mut self,
+        x: &[F],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send;
+
+    // offline phase: runs n OLEs on random inputs x, to be derandomized later by
+    // RandomOleReceiver::input_async once the actual inputs are known
+    async fn input_random<F, S, Crng>(
+        &mut self,
+        n: usize,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<RandomOleReceiver<F>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let x: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let y = self.input(&x, channel, rng).await?;
+        Ok(RandomOleReceiver::new(x, y))
+    }
+}
+
+// Wraps an OleSender, whose OT-extension and pool are used from the blocking jobs
+pub struct AsyncOleSender {
+    ole: Arc<Mutex<OleSender>>,
+}
+
+#[async_trait]
+impl AsyncSender for AsyncOleSender {
+    async fn init<S, Crng>(channel: &mut AsyncChannel<S>, rng: &mut Crng) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        let ole = channel
+            .run_blocking(move |c| OleSender::init(c, &mut ot_rng))
+            .await?;
+        Ok(Self::new(ole))
+    }
+
+    // Inputs of any length are split into instances of params.batch_size() OLEs each,
+    // the last instance is padded with random values.
+    async fn input_with_params<F, S, Crng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        assert_eq!(a.len(), b.len());
+        self.ole.lock().unwrap().check_pool(a.len(), params)?;
+
+        let n = params.batch_size();
+        let instances: Vec<(&[F], &[F])> = a.chunks(n).zip(b.chunks(n)).collect();
+        for round in instances.chunks(ROUND_INSTANCES) {
+            self.input_round(round, params, channel, rng).await?;
+        }
+        return Ok(());
+    }
+}
+
+impl AsyncOleSender {
+    fn new(ole: OleSender) -> Self {
+        Self {
+            ole: Arc::new(Mutex::new(ole)),
+        }
+    }
+
+    // see OleSender::precompute
+    pub async fn precompute<F, S, Crng>(
+        &mut self,
+        n: usize,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        self.precompute_with_params(n, &OleParams::<F>::default(), channel, rng)
+            .await
+    }
+
+    // see OleSender::precompute_with_params
+    pub async fn precompute_with_params<F, S, Crng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let num = num_ots(n, params);
+        let ole = self.ole.clone();
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        channel
+            .run_blocking(move |c| ole.lock().unwrap().precompute_ots(num, c, &mut ot_rng))
+            .await
+    }
+
+    // see OleSender::save_pool
+    pub fn save_pool(self, session_id: &Block) -> Vec<u8> {
+        return self.ole.lock().unwrap().take_pool(session_id);
+    }
+
+    // see OleSender::restore_pool
+    pub async fn restore_pool<S>(
+        bytes: &mut [u8],
+        channel: &mut AsyncChannel<S>,
+    ) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let mut state = bytes.to_vec();
+        let ole = channel
+            .run_blocking(move |c| OleSender::restore_pool(&mut state, c))
+            .await?;
+        ot_pool::invalidate(bytes);
+        Ok(Self::new(ole))
+    }
+
+    // runs a round of OLE instances like OleSender::input_round, with all of its OTs in
+    // one blocking job
+    async fn input_round<F, S, Crng>(
+        &mut self,
+        instances: &[(&[F], &[F])],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let mut round = SenderRound::commit(instances, params, rng)?;
+        channel.write_bytes(&round.commitments()).await?;
+        channel.flush().await?;
+
+        let ot_inputs: Vec<Vec<(Block, Block)>> = round.ot_inputs().map(|i| i.to_vec()).collect();
+        let ole = self.ole.clone();
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        channel
+            .run_blocking(move |c| {
+                let mut ole = ole.lock().unwrap();
+                for ot_input in ot_inputs.iter() {
+                    ole.ot_send(c, ot_input, &mut ot_rng)?;
+                }
+                Ok(())
+            })
+            .await?;
+        channel.write_bytes(&round.ot_payloads()).await?;
+        channel.flush().await?;
+
+        let mut vs = vec![0u8; round.encodings_len()];
+        channel.read_bytes(&mut vs).await?;
+        channel.write_bytes(&round.respond(&vs)?).await?;
+        channel.flush().await?;
+
+        let mut zr_seeds = vec![0u8; round.challenges_len()];
+        channel.read_bytes(&mut zr_seeds).await?;
+        channel.write_bytes(&round.open(&zr_seeds, rng)).await?;
+        channel.flush().await?;
+
+        let mut evaluations = vec![0u8; round.evaluations_len()];
+        channel.read_bytes(&mut evaluations).await?;
+        return round.check(&evaluations);
+    }
+}
+
+// Wraps an OleReceiver, whose OT-extension and pool are used from the blocking jobs
+pub struct AsyncOleReceiver {
+    ole: Arc<Mutex<OleReceiver>>,
+}
+
+#[async_trait]
+impl AsyncReceiver for AsyncOleReceiver {
+    async fn init<S, Crng>(channel: &mut AsyncChannel<S>, rng: &mut Crng) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        let ole = channel
+            .run_blocking(move |c| OleReceiver::init(c, &mut ot_rng))
+            .await?;
+        Ok(Self::new(ole))
+    }
+
+    // Inputs of any length are split into instances of params.batch_size() OLEs each,
+    // the last instance is padded with random values. Returns exactly x.len() outputs.
+    async fn input_with_params<F, S, Crng>(
+        &mut self,
+        x: &[F],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        self.ole.lock().unwrap().check_pool(x.len(), params)?;
+
+        let mut result = Vec::with_capacity(x.len());
+        let instances: Vec<&[F]> = x.chunks(params.batch_size()).collect();
+        for round in instances.chunks(ROUND_INSTANCES) {
+            let ys = self.input_round(round, params, channel, rng).await?;
+            for (x_chunk, mut y) in round.iter().zip(ys) {
+                y.truncate(x_chunk.len());
+                result.append(&mut y);
+            }
+        }
+        return Ok(result);
+    }
+}
+
+impl AsyncOleReceiver {
+    fn new(ole: OleReceiver) -> Self {
+        Self {
+            ole: Arc::new(Mutex::new(ole)),
+        }
+    }
+
+    // see OleReceiver::precompute
+    pub async fn precompute<F, S, Crng>(
+        &mut self,
+        n: usize,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        self.precompute_with_params(n, &OleParams::<F>::default(), channel, rng)
+            .await
+    }
+
+    // see OleReceiver::precompute_with_params
+    pub async fn precompute_with_params<F, S, Crng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<(), OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let num = num_ots(n, params);
+        let ole = self.ole.clone();
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        channel
+            .run_blocking(move |c| ole.lock().unwrap().precompute_ots(num, c, &mut ot_rng))
+            .await
+    }
+
+    // see OleReceiver::save_pool
+    pub fn save_pool(self, session_id: &Block) -> Vec<u8> {
+        return self.ole.lock().unwrap().take_pool(session_id);
+    }
+
+    // see OleReceiver::restore_pool
+    pub async fn restore_pool<S>(
+        bytes: &mut [u8],
+        channel: &mut AsyncChannel<S>,
+    ) -> Result<Self, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let mut state = bytes.to_vec();
+        let ole = channel
+            .run_blocking(move |c| OleReceiver::restore_pool(&mut state, c))
+            .await?;
+        ot_pool::invalidate(bytes);
+        Ok(Self::new(ole))
+    }
+
+    // runs a round of OLE instances like OleReceiver::input_round, with all of its OTs
+    // in one blocking job. Returns the outputs of each instance
+    async fn input_round<F, S, Crng>(
+        &mut self,
+        instances: &[&[F]],
+        params: &OleParams<F>,
+        channel: &mut AsyncChannel<S>,
+        rng: &mut Crng,
+    ) -> Result<Vec<Vec<F>>, OleError>
+    where
+        F: OleField,
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+        Crng: CryptoRng + Rng + Send,
+    {
+        let mut round = ReceiverRound::encode(instances, params, rng);
+        let mut coms = vec![0u8; round.commitments_len()];
+        channel.read_bytes(&mut coms).await?;
+
+        let choices: Vec<Vec<bool>> = round.ot_choices().map(|c| c.to_vec()).collect();
+        let ole = self.ole.clone();
+        let mut ot_rng = AesRng::from_seed(rng.gen::<Block>());
+        let vals = channel
+            .run_blocking(move |c| {
+                let mut ole = ole.lock().unwrap();
+                choices
+                    .iter()
+                    .map(|choices| ole.ot_receive(c, choices, &mut ot_rng))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .await?;
+        let mut ot_payloads = vec![0u8; round.ot_payloads_len()];
+        channel.read_bytes(&mut ot_payloads).await?;
+        channel
+            .write_bytes(&round.open(&coms, vals, &ot_payloads)?)
+            .await?;
+        channel.flush().await?;
+
+        let mut wss = vec![0u8; round.responses_len()];
+        channel.read_bytes(&mut wss).await?;
+        channel.write_bytes(&round.decode(&wss, rng)?).await?;
+        channel.flush().await?;
+
+        let mut openings = vec![0u8; round.openings_len()];
+        channel.read_bytes(&mut openings).await?;
+        channel.write_bytes(&round.check(&openings)?).await?;
+        channel.flush().await?;
+
+        return Ok(round.output());
+    }
+}
+
+impl<F: OleField> RandomOleSender<F> {
+    // RandomOleSender::input over an AsyncChannel
+    pub async fn input_async<S>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        channel: &mut AsyncChannel<S>,
+    ) -> Result<(), OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        assert_eq!(a.len(), b.len());
+        if a.len() > self.len() {
+            return Err(OleError::RandomOlePoolExhausted);
+        }
+
+        let e: Vec<F> = channel.read_elements(a.len()).await?;
+        channel.write_bytes(&self.respond(a, b, &e)).await?;
+        channel.flush().await?;
+
+        return Ok(());
+    }
+}
+
+impl<F: OleField> RandomOleReceiver<F> {
+    // RandomOleReceiver::input over an AsyncChannel
+    pub async fn input_async<S>(
+        &mut self,
+        x: &[F],
+        channel: &mut AsyncChannel<S>,
+    ) -> Result<Vec<F>, OleError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        if x.len() > self.len() {
+            return Err(OleError::RandomOlePoolExhausted);
+        }
+
+        let (x0, y0, e) = self.blind(x);
+        channel.write_elements(&e).await?;
+        channel.flush().await?;
+
+        let d: Vec<F> = channel.read_elements(x.len()).await?;
+        let t: Vec<F> = channel.read_elements(x.len()).await?;
+        return Ok(Self::unblind(&x0, &y0, &d, &t));
+    }
+}
+
+// Buffered channel over an async read/write transport
+pub struct AsyncChannel<S> {
+    stream: S,
+    read_buffer: VecDeque<u8>,
+    write_buffer: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> AsyncChannel<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: stream,
+            read_buffer: VecDeque::new(),
+            write_buffer: vec![],
+        }
+    }
+
+    pub async fn read_bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        let n = std::cmp::min(bytes.len(), self.read_buffer.len());
+        for (byte, buffered) in bytes.iter_mut().zip(self.read_buffer.drain(..n)) {
+            *byte = buffered;
+        }
+        self.stream.read_exact(&mut bytes[n..]).await?;
+        Ok(())
+    }
+
+    pub async fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_buffer.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.stream.write_all(&self.write_buffer).await?;
+        self.write_buffer.clear();
+        self.stream.flush().await
+    }
+
+    pub async fn read_block(&mut self) -> io::Result<Block> {
+        let mut block = Block::default();
+        self.read_bytes(block.as_mut()).await?;
+        Ok(block)
+    }
+
+    pub async fn read_blocks(&mut self, n: usize) -> io::Result<Vec<Block>> {
+        let mut blocks = Vec::with_capacity(n);
+        for _ in 0..n {
+            blocks.push(self.read_block().await?);
+        }
+        Ok(blocks)
+    }
+
+    pub async fn write_block(&mut self, block: &Block) -> io::Result<()> {
+        self.write_bytes(block.as_ref()).await
+    }
+
+    pub async fn read_elements<F: OleField>(&mut self, n: usize) -> Result<Vec<F>, OleError> {
+        let mut bytes = vec![0u8; n * F::BYTES];
+        self.read_bytes(&mut bytes).await?;
+        return to_elements(&bytes);
+    }
+
+    pub async fn write_elements<F: OleField>(&mut self, elements: &[F]) -> io::Result<()> {
+        self.write_bytes(&to_bytes(elements)).await
+    }
+
+    // Runs f on the blocking thread pool with a BridgeChannel, while forwarding its
+    // traffic to and from the transport. Bytes read from the transport that f didn't
+    // consume are kept for the following reads.
+    async fn run_blocking<R, Fun>(&mut self, f: Fun) -> Result<R, OleError>
+    where
+        R: Send + 'static,
+        Fun: FnOnce(&mut BridgeChannel) -> Result<R, OleError> + Send + 'static,
+    {
+        self.flush().await?;
+
+        let (incoming_tx, incoming_rx) = mpsc::channel::<Vec<u8>>();
+        let (outgoing_tx, mut outgoing_rx) = async_mpsc::unbounded_channel::<Vec<u8>>();
+        if !self.read_buffer.is_empty() {
+            let _ = incoming_tx.send(self.read_buffer.drain(..).collect());
+        }
+
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut channel = BridgeChannel::new(incoming_rx, outgoing_tx);
+            let result = f(&mut channel);
+            (result, channel.finish())
+        });
+
+        // The two directions are forwarded concurrently, so that a write waiting for the
+        // peer to read never holds up the reads the peer may be waiting on. Outgoing
+        // bytes are done once the blocking side dropped its queue, incoming ones only
+        // stop with f, or fail.
+        let (mut reader, mut writer) = tokio::io::split(&mut self.stream);
+        let forward_outgoing = async move {
+            while let Some(bytes) = outgoing_rx.recv().await {
+                writer.write_all(&bytes).await?;
+                writer.flush().await?;
+            }
+            Ok::<(), io::Error>(())
+        };
+        let forward_incoming = async move {
+            let mut buf = vec![0u8; 1 << 16];
+            loop {
+                let n = match reader.read(&mut buf).await {
+                    Ok(n) => n,
+                    Err(e) => return e,
+                };
+                if n == 0 {
+                    // dropping the sender lets the blocking side fail on its next read
+                    drop(incoming_tx);
+                    return std::future::pending::<io::Error>().await;
+                }
+                let _ = incoming_tx.send(buf[..n].to_vec());
+            }
+        };
+        let run = async {
+            let (joined, forwarded) = tokio::join!(handle, forward_outgoing);
+            forwarded?;
+            joined.map_err(io::Error::other)
+        };
+        let (result, (mut leftover, incoming_rx)) = tokio::select! {
+            joined = run => joined?,
+            e = forward_incoming => return Err(e.into()),
+        };
+
+        // bytes forwarded after f returned are still queued
+        for bytes in incoming_rx.try_iter() {
+            leftover.extend(bytes);
+        }
+        self.read_buffer.extend(leftover);
+
+        return result;
+    }
+}
+
+// Blocking AbstractChannel used by the OT-extension, connected to an AsyncChannel by
+// in-memory queues
+struct BridgeChannel {
+    inner: Rc<RefCell<BridgeInner>>,
+}
+
+struct BridgeInner {
+    incoming: mpsc::Receiver<Vec<u8>>,
+    outgoing: async_mpsc::UnboundedSender<Vec<u8>>,
+    read_buffer: VecDeque<u8>,
+    write_buffer: Vec<u8>,
+}
+
+impl BridgeChannel {
+    fn new(
+        incoming: mpsc::Receiver<Vec<u8>>,
+        outgoing: async_mpsc::UnboundedSender<Vec<u8>>,
+    ) -> Self {
+        let inner = BridgeInner {
+            incoming: incoming,
+            outgoing: outgoing,
+            read_buffer: VecDeque::new(),
+            write_buffer: vec![],
+        };
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+        }
+    }
+
+    // Returns the bytes received but not read, and the queue of incoming bytes, which
+    // the AsyncChannel may still forward to. There are no clones of the channel left
+    // once run_blocking's f returned, as they are not Send.
+    fn finish(self) -> (Vec<u8>, mpsc::Receiver<Vec<u8>>) {
+        let inner = match Rc::try_unwrap(self.inner) {
+            Ok(inner) => inner.into_inner(),
+            Err(_) => unreachable!(),
+        };
+        (inner.read_buffer.into_iter().collect(), inner.incoming)
+    }
+}
+
+impl AbstractChannel for BridgeChannel {
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        while inner.read_buffer.len() < bytes.len() {
+            let received = inner
+                .incoming
+                .recv()
+                .map_err(|_| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            inner.read_buffer.extend(received);
+        }
+        let n = bytes.len();
+        for (byte, buffered) in bytes.iter_mut().zip(inner.read_buffer.drain(..n)) {
+            *byte = buffered;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner
+            .borrow_mut()
+            .write_buffer
+            .extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        if !inner.write_buffer.is_empty() {
+            let bytes = std::mem::take(&mut inner.write_buffer);
+            inner
+                .outgoing
+                .send(bytes)
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        }
+        Ok(())
+    }
+
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Fp;
+    use crate::field255::Fp255;
+    use ff::Field;
+
+    #[tokio::test]
+    async fn test_async_ole() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = tokio::io::duplex(1 << 12);
+        let n = Fp::A / 2 + 10;
+        let a: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let x: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+
+        let run_sender = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(sender);
+            let mut olesender = AsyncOleSender::init(&mut channel, &mut rng).await.unwrap();
+            olesender
+                .input(&a, &b, &mut channel, &mut rng)
+                .await
+                .unwrap();
+        };
+        let run_receiver = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(receiver);
+            let mut olereceiver = AsyncOleReceiver::init(&mut channel, &mut rng)
+                .await
+                .unwrap();
+            olereceiver.input(&x, &mut channel, &mut rng).await.unwrap()
+        };
+        let (_, result) = tokio::join!(run_sender, run_receiver);
+
+        assert_eq!(result.len(), n);
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_sync_ole() {
+        // more than one round of small instances, and elements sent in OT payloads
+        let small = OleParams::<Fp>::new(8, 27, 19).unwrap();
+        check_async_sync_ole(small, (ROUND_INSTANCES + 2) * 4).await;
+        check_async_sync_ole(OleParams::<Fp255>::default(), Fp255::A).await;
+    }
+
+    // runs an async sender with a sync receiver, and a sync sender with an async receiver
+    async fn check_async_sync_ole<F: OleField>(params: OleParams<F>, n: usize) {
+        let mut rng = AesRng::new();
+        let a: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let x: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let mut expected = x.clone();
+        F::batch_mul(&mut expected, &a);
+        F::batch_add(&mut expected, &b);
+
+        let (sender, receiver) = tokio::io::duplex(1 << 12);
+        let run_sender = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(sender);
+            let mut olesender = AsyncOleSender::init(&mut channel, &mut rng).await.unwrap();
+            olesender
+                .input_with_params(&a, &b, &params, &mut channel, &mut rng)
+                .await
+                .unwrap();
+        };
+        let (x_copy, params_copy) = (x.clone(), params.clone());
+        let run_receiver = async {
+            let mut channel = AsyncChannel::new(receiver);
+            channel
+                .run_blocking(move |c| {
+                    let mut rng = AesRng::new();
+                    let mut olereceiver = OleReceiver::init(c, &mut rng)?;
+                    olereceiver.input_with_params(&x_copy, &params_copy, c, &mut rng)
+                })
+                .await
+                .unwrap()
+        };
+        let (_, result) = tokio::join!(run_sender, run_receiver);
+        assert_eq!(result, expected);
+
+        let (sender, receiver) = tokio::io::duplex(1 << 12);
+        let (a_copy, b_copy, params_copy) = (a.clone(), b.clone(), params.clone());
+        let run_sender = async {
+            let mut channel = AsyncChannel::new(sender);
+            channel
+                .run_blocking(move |c| {
+                    let mut rng = AesRng::new();
+                    let mut olesender = OleSender::init(c, &mut rng)?;
+                    olesender.input_with_params(&a_copy, &b_copy, &params_copy, c, &mut rng)
+                })
+                .await
+                .unwrap();
+        };
+        let run_receiver = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(receiver);
+            let mut olereceiver = AsyncOleReceiver::init(&mut channel, &mut rng)
+                .await
+                .unwrap();
+            olereceiver
+                .input_with_params(&x, &params, &mut channel, &mut rng)
+                .await
+                .unwrap()
+        };
+        let (_, result) = tokio::join!(run_sender, run_receiver);
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn test_async_random_ole() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = tokio::io::duplex(1 << 12);
+        let n = 200;
+        let a: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let x: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+
+        // the random OLEs take their OTs from the precomputed pool
+        let run_sender = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(sender);
+            let mut olesender = AsyncOleSender::init(&mut channel, &mut rng).await.unwrap();
+            olesender
+                .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                .await
+                .unwrap();
+            let mut pool = olesender
+                .input_random::<Fp, _, _>(n, &mut channel, &mut rng)
+                .await
+                .unwrap();
+            pool.input_async(&a, &b, &mut channel).await.unwrap();
+            assert!(pool.is_empty());
+            match pool.input_async(&a[..1], &b[..1], &mut channel).await {
+                Err(OleError::RandomOlePoolExhausted) => (),
+                r => panic!("expected RandomOlePoolExhausted, got {:?}", r),
+            }
+        };
+        let run_receiver = async {
+            let mut rng = AesRng::new();
+            let mut channel = AsyncChannel::new(receiver);
+            let mut olereceiver = AsyncOleReceiver::init(&mut channel, &mut rng)
+                .await
+                .unwrap();
+            olereceiver
+                .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                .await
+                .unwrap();
+            let mut pool = olereceiver
+                .input_random::<Fp, _, _>(n, &mut channel, &mut rng)
+                .await
+                .unwrap();
+            let result = pool.input_async(&x, &mut channel).await.unwrap();
+            match pool.input_async(&x[..1], &mut channel).await {
+                Err(OleError::RandomOlePoolExhausted) => (),
+                r => panic!("expected RandomOlePoolExhausted, got {:?}", r),
+            }
+            result
+        };
+        let (_, result) = tokio::join!(run_sender, run_receiver);
+
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+}
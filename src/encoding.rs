@@ -1,35 +1,48 @@
 use crate::field::OleField;
-use crate::poly::{euclid_division, poly_from_roots};
+use crate::params::OleParams;
+use crate::poly::{fast_euclid_division_with, poly_from_roots};
 use rand::{seq::IteratorRandom, CryptoRng, Rng};
 
-pub fn decode_reed_solomon<F: OleField>(points: &mut [F], pos: &[usize]) -> Vec<F> {
-    F::fft3_inverse(points, &F::beta());
-    let roots: Vec<F> = pos.iter().map(|idx| F::beta().pow([*idx as u64])).collect();
+// Interpolates the polynomial through points at the positions pos, where points are
+// evaluations at the first points.len() powers of beta
+pub fn decode_reed_solomon<F: OleField>(
+    points: &[F],
+    pos: &[usize],
+    params: &OleParams<F>,
+) -> Vec<F> {
+    let mut points = points.to_vec();
+    points.resize_with(params.beta_order(), F::zero);
+    params.beta_domain().fft_inverse(&mut points);
+    let roots: Vec<F> = pos
+        .iter()
+        .map(|idx| params.beta_domain().element(*idx))
+        .collect();
     let b = poly_from_roots(&roots);
-    let (_, r) = euclid_division(points, &b);
+    let (_, r) = fast_euclid_division_with(&points, &b, params.mul_domains());
     return r;
 }
 
 pub fn encode_reed_solomon<F: OleField, Crng: CryptoRng + Rng>(
     x: &[F],
+    params: &OleParams<F>,
     rng: &mut Crng,
 ) -> (Vec<F>, Vec<F>, Vec<usize>) {
-    let pos = pick_indices(F::A, F::B, rng);
+    let pos = pick_indices(params.alpha_order(), params.code_length(), rng);
 
     // let mut x_padded = pad_every_other(x, rng);
     // for (i, x_) in x.iter().enumerate() {
     //     assert_eq!(*x_, x_padded[2*i]);
     // }
-    let mut x_padded = x.to_vec();
-    x_padded.resize_with(F::A / 2, || F::random(rng));
-    F::fft2_inverse(&mut x_padded, &F::alpha().pow([2]));
-    let x_poly = x_padded.to_vec();
-    x_padded.resize_with(F::B, F::zero);
-    F::fft3(&mut x_padded, &F::beta());
+    let mut x_poly = x.to_vec();
+    x_poly.resize_with(params.batch_size(), || F::random(rng));
+    params.input_domain().fft_inverse(&mut x_poly);
+    let mut encoded = params
+        .beta_domain()
+        .fft_pruned(&x_poly, params.code_length());
 
     let pos_len = pos.len();
     let mut j = 0;
-    for (i, x) in x_padded.iter_mut().enumerate() {
+    for (i, x) in encoded.iter_mut().enumerate() {
         if j < pos_len && i == pos[j] {
             j += 1;
             continue;
@@ -38,7 +51,7 @@ pub fn encode_reed_solomon<F: OleField, Crng: CryptoRng + Rng>(
         }
     }
 
-    return (x_padded, x_poly, pos);
+    return (encoded, x_poly, pos);
 }
 
 pub fn pad_every_other<F: OleField, Crng: CryptoRng + Rng>(input: &[F], rng: &mut Crng) -> Vec<F> {
@@ -63,14 +76,19 @@ pub fn pick_indices<Crng: CryptoRng + Rng>(l: usize, n: usize, rng: &mut Crng) -
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::field::Fp;
+    use crate::field::{Fp, OleField};
+    use crate::field64::Fp64;
     use crate::poly;
-    use ff::Field;
 
     #[test]
     fn test_pad_every_other() {
+        check_pad_every_other::<Fp>();
+        check_pad_every_other::<Fp64>();
+    }
+
+    fn check_pad_every_other<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let points: Vec<Fp> = (0..100).map(|_| Fp::random(&mut rng)).collect();
+        let points: Vec<F> = (0..100).map(|_| F::random(&mut rng)).collect();
 
         let padded = pad_every_other(&points, &mut rng);
         for (i, point) in points.iter().enumerate() {
@@ -80,20 +98,25 @@ mod tests {
 
     #[test]
     fn test_encode_decode() {
+        check_encode_decode::<Fp>();
+        check_encode_decode::<Fp64>();
+    }
+
+    fn check_encode_decode<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let points: Vec<Fp> = (0..(Fp::A / 2) - 1).map(|_| Fp::random(&mut rng)).collect();
+        let points: Vec<F> = (0..(F::A / 2) - 1).map(|_| F::random(&mut rng)).collect();
 
-        let (mut encoded, poly, pos) = encode_reed_solomon(&points, &mut rng);
+        let (encoded, poly, pos) = encode_reed_solomon(&points, &OleParams::default(), &mut rng);
         for (i, p) in points.iter().enumerate() {
-            assert_eq!(*p, poly::horner(&poly, &Fp::alpha().pow([(2 * i) as u64])));
+            assert_eq!(*p, poly::horner(&poly, &F::alpha().pow([(2 * i) as u64])));
         }
-        assert!(encoded.len() == Fp::B);
-        assert!(poly.len() == Fp::A / 2);
-        assert!(pos.len() == Fp::A);
+        assert!(encoded.len() == F::B);
+        assert!(poly.len() == F::A / 2);
+        assert!(pos.len() == F::A);
 
-        let mut decoded_poly = decode_reed_solomon(&mut encoded, &pos);
+        let mut decoded_poly = decode_reed_solomon(&encoded, &pos, &OleParams::default());
         println!("len decoded: {}", decoded_poly.len());
-        Fp::fft2(&mut decoded_poly, &Fp::alpha().pow([2]));
+        F::fft2(&mut decoded_poly, &F::alpha().pow([2]));
 
         for (idx, p) in points.iter().enumerate() {
             assert_eq!(*p, decoded_poly[idx]);
@@ -102,34 +125,40 @@ mod tests {
 
     #[test]
     fn test_encode_decode_linear_operations() {
+        check_encode_decode_linear_operations::<Fp>();
+        check_encode_decode_linear_operations::<Fp64>();
+    }
+
+    fn check_encode_decode_linear_operations<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let points: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
+        let points: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
 
-        let (mut encoded, _poly, pos) = encode_reed_solomon(&points, &mut rng);
+        let (mut encoded, _poly, pos) =
+            encode_reed_solomon(&points, &OleParams::default(), &mut rng);
 
-        let mut a: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
+        let mut a: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
         let a_copy = a.to_vec();
-        Fp::fft2_inverse(&mut a, &Fp::alpha().pow([2]));
+        F::fft2_inverse(&mut a, &F::alpha().pow([2]));
 
-        let b_copy: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
+        let b_copy: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
         let mut b = pad_every_other(&b_copy, &mut rng);
-        Fp::fft2_inverse(&mut b, &Fp::alpha());
+        F::fft2_inverse(&mut b, &F::alpha());
 
-        for i in 0..Fp::A / 2 {
+        for i in 0..F::A / 2 {
             assert_eq!(
                 a_copy[i],
-                poly::horner(&a, &Fp::alpha().pow([(2 * i) as u64]))
+                poly::horner(&a, &F::alpha().pow([(2 * i) as u64]))
             );
             assert_eq!(
                 b_copy[i],
-                poly::horner(&b, &Fp::alpha().pow([(2 * i) as u64]))
+                poly::horner(&b, &F::alpha().pow([(2 * i) as u64]))
             );
         }
 
-        a.resize_with(Fp::B, Fp::zero);
-        Fp::fft3(&mut a, &Fp::beta());
-        b.resize_with(Fp::B, Fp::zero);
-        Fp::fft3(&mut b, &Fp::beta());
+        a.resize_with(F::B, F::zero);
+        F::fft3(&mut a, &F::beta());
+        b.resize_with(F::B, F::zero);
+        F::fft3(&mut b, &F::beta());
 
         // Modify the encoding as in the protocol
         let mut j = 0;
@@ -137,16 +166,16 @@ mod tests {
             x.mul_assign(&a[idx]);
             x.add_assign(&b[idx]);
             if j < pos.len() && idx != pos[j] {
-                x.add_assign(&Fp::random(&mut rng));
+                x.add_assign(&F::random(&mut rng));
             } else {
                 j += 1;
             }
         }
 
-        let mut decoded_poly = decode_reed_solomon(&mut encoded, &pos);
+        let mut decoded_poly = decode_reed_solomon(&encoded, &pos, &OleParams::default());
         // let decoded_copy = decoded_poly.to_vec();
         println!("len decoded: {}", decoded_poly.len());
-        Fp::fft2(&mut decoded_poly, &Fp::alpha());
+        F::fft2(&mut decoded_poly, &F::alpha());
 
         for (idx, p) in points.iter().enumerate() {
             let mut expected = *p;
@@ -155,4 +184,27 @@ mod tests {
             assert_eq!(expected, decoded_poly[2 * idx]);
         }
     }
+
+    #[test]
+    fn test_encode_decode_params() {
+        check_encode_decode_params::<Fp>();
+        check_encode_decode_params::<Fp64>();
+    }
+
+    fn check_encode_decode_params<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let params = OleParams::<F>::new(64, 729, 200).unwrap();
+        let points: Vec<F> = (0..params.batch_size())
+            .map(|_| F::random(&mut rng))
+            .collect();
+
+        let (encoded, _poly, pos) = encode_reed_solomon(&points, &params, &mut rng);
+        assert_eq!(encoded.len(), params.code_length());
+        assert_eq!(pos.len(), params.alpha_order());
+
+        let mut decoded_poly = decode_reed_solomon(&encoded, &pos, &params);
+        decoded_poly.resize_with(params.batch_size(), F::zero);
+        F::fft2(&mut decoded_poly, &params.alpha().pow([2]));
+        assert_eq!(decoded_poly, points);
+    }
 }
@@ -1,17 +1,61 @@
+use ff::PrimeFieldDecodingError;
 use ocelot::Error as OcelotError;
 use std::error;
 use std::fmt;
 
 #[derive(Debug)]
 pub enum OleError {
-    TODOError,
     IOError(std::io::Error),
     OTError(OcelotError),
+    // The sender's commitment to the shared secret does not match the reconstructed secret
+    CommitmentMismatch,
+    // The sender's consistency check at its challenge point zs failed, i.e. the receiver cheated
+    SenderCheckFailed,
+    // The receiver's consistency check at its challenge point zr failed, i.e. the sender cheated
+    ReceiverCheckFailed,
+    // A sacrificed triple revealed that a Beaver triple is incorrect
+    TripleCheckFailed,
+    // The parties restored OT state of different sessions, or one of them stale state
+    SessionMismatch,
+    // The saved OT state was restored before, and its OTs may have been used
+    StateReplayed,
+    // A restored sender or receiver lacks the precomputed OTs for an input, and has no
+    // OT-extension to fall back on until precompute refills its pool
+    OTPoolExhausted,
+    // An input to a random OLE pool is longer than the random OLEs left in the pool
+    RandomOlePoolExhausted,
+    // The OLE parameters are not valid for the field
+    InvalidParams(&'static str),
+    // The peer sent a block that doesn't encode a field element
+    DecodingError(PrimeFieldDecodingError),
 }
 
 impl fmt::Display for OleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "something went wrong")
+        match self {
+            OleError::IOError(e) => write!(f, "io error: {}", e),
+            OleError::OTError(e) => write!(f, "oblivious transfer error: {}", e),
+            OleError::CommitmentMismatch => {
+                write!(f, "cheating detected: sender's commitment does not match")
+            }
+            OleError::SenderCheckFailed => {
+                write!(f, "cheating detected: sender's consistency check failed")
+            }
+            OleError::ReceiverCheckFailed => {
+                write!(f, "cheating detected: receiver's consistency check failed")
+            }
+            OleError::TripleCheckFailed => {
+                write!(f, "cheating detected: triple sacrifice check failed")
+            }
+            OleError::SessionMismatch => write!(f, "restored OT state does not match the session"),
+            OleError::StateReplayed => write!(f, "saved OT state was already restored"),
+            OleError::OTPoolExhausted => write!(f, "precomputed OTs are used up"),
+            OleError::RandomOlePoolExhausted => {
+                write!(f, "input is longer than the random OLEs left in the pool")
+            }
+            OleError::InvalidParams(e) => write!(f, "invalid OLE parameters: {}", e),
+            OleError::DecodingError(e) => write!(f, "invalid field element: {}", e),
+        }
     }
 }
 
@@ -33,3 +77,9 @@ impl From<std::io::Error> for OleError {
         OleError::IOError(e)
     }
 }
+
+impl From<PrimeFieldDecodingError> for OleError {
+    fn from(e: PrimeFieldDecodingError) -> OleError {
+        OleError::DecodingError(e)
+    }
+}
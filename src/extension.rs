@@ -0,0 +1,509 @@
+use crate::field::{div_exact, BatchField, FiniteField, OleField};
+use ff::{Field, PrimeField, PrimeFieldDecodingError};
+use rand_core::RngCore;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Extension field GF(p^K) = F[x]/(m(x)) of the prime field F = GF(p), for primes where
+// p-1 has no smooth subgroups to run OLE in, but p^K-1 does. Elements are polynomials
+// c_0 + c_1*x + ... + c_(K-1)*x^(K-1) over F, multiplied modulo the irreducible m(x).
+//
+// The modulus, a multiplicative generator, the subgroup orders and their generators are
+// given by P:
+//
+//     struct Fq2Params;
+//     impl ExtensionParams<Fq, 2> for Fq2Params { ... }
+//     type Fq2 = ExtensionField<Fq, Fq2Params, 2>;
+
+pub trait ExtensionParams<F: PrimeField, const K: usize>: 'static + Send + Sync {
+    // coefficients m_0, ..., m_(K-1) of the modulus
+    // m(x) = x^K + m_(K-1)*x^(K-1) + ... + m_0, which must be irreducible over F
+    fn modulus() -> [F; K];
+
+    // generator of the multiplicative group of GF(p^K), as coefficients c_0, ..., c_(K-1)
+    fn generator() -> [F; K];
+
+    const A: usize; // order of alpha(), a power of 2 dividing p^K-1
+    const B: usize; // order of beta(), a power of 3 dividing p^K-1
+
+    // alpha() and beta() as coefficients, i.e. generator()^((p^K-1)/A) and
+    // generator()^((p^K-1)/B), which subgroup_generator(A) and subgroup_generator(B)
+    // compute. Given here as the OLE parameters evaluate them on every use
+    fn alpha() -> [F; K];
+    fn beta() -> [F; K];
+}
+
+pub struct ExtensionField<F, P, const K: usize> {
+    coeffs: [F; K],
+    params: PhantomData<P>,
+}
+
+impl<F: PrimeField, P: ExtensionParams<F, K>, const K: usize> ExtensionField<F, P, K> {
+    pub fn new(coeffs: [F; K]) -> Self {
+        Self {
+            coeffs: coeffs,
+            params: PhantomData,
+        }
+    }
+
+    pub fn coeffs(&self) -> &[F; K] {
+        &self.coeffs
+    }
+
+    // p^K - sub as little-endian u64 limbs
+    fn order_minus(sub: u64) -> Vec<u64> {
+        let p = F::char();
+        let mut order = vec![1u64];
+        for _ in 0..K {
+            order = mul_limbs(&order, p.as_ref());
+        }
+        let mut borrow = sub;
+        for limb in order.iter_mut() {
+            let (diff, overflow) = limb.overflowing_sub(borrow);
+            *limb = diff;
+            if !overflow {
+                break;
+            }
+            borrow = 1;
+        }
+        return order;
+    }
+
+    // Rabin's test: m(x) of degree K is irreducible over F iff x^(p^K) = x mod m(x), and
+    // gcd(x^(p^(K/r)) - x, m(x)) = 1 for every prime r dividing K
+    pub fn modulus_is_irreducible() -> bool {
+        if K == 1 {
+            return true;
+        }
+        let mut x = [F::zero(); K];
+        x[1] = F::one();
+        let x = Self::new(x);
+
+        let mut x_pk = x;
+        x_pk.frobenius_map(K);
+        if x_pk != x {
+            return false;
+        }
+        let mut modulus = P::modulus().to_vec();
+        modulus.push(F::one());
+        for r in (2..=K).filter(|r| K.is_multiple_of(*r) && (2..*r).all(|d| r % d != 0)) {
+            let mut g = x;
+            g.frobenius_map(K / r);
+            g.sub_assign(&x);
+            if poly_gcd(g.coeffs.to_vec(), modulus.clone()).len() != 1 {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+// gcd of polynomials over F given by their coefficients, up to a constant factor. The
+// zero polynomial is the empty vector
+fn poly_gcd<F: PrimeField>(mut a: Vec<F>, mut b: Vec<F>) -> Vec<F> {
+    trim_zeros(&mut a);
+    trim_zeros(&mut b);
+    while !b.is_empty() {
+        // a mod b, cancelling the top coefficient of a
+        let lead_inv = b[b.len() - 1].inverse().unwrap();
+        while a.len() >= b.len() {
+            let mut c = a[a.len() - 1];
+            c.mul_assign(&lead_inv);
+            let shift = a.len() - b.len();
+            for (i, bi) in b.iter().enumerate() {
+                let mut t = *bi;
+                t.mul_assign(&c);
+                a[shift + i].sub_assign(&t);
+            }
+            a.pop();
+            trim_zeros(&mut a);
+        }
+        std::mem::swap(&mut a, &mut b);
+    }
+    return a;
+}
+
+fn trim_zeros<F: PrimeField>(poly: &mut Vec<F>) {
+    while !poly.is_empty() && poly[poly.len() - 1].is_zero() {
+        poly.pop();
+    }
+}
+
+// product of integers given as little-endian u64 limbs
+fn mul_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut result = vec![0u64; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, y) in b.iter().enumerate() {
+            let t = (*x as u128) * (*y as u128) + result[i + j] as u128 + carry;
+            result[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        result[i + b.len()] = carry as u64;
+    }
+    return result;
+}
+
+impl<F: Copy, P, const K: usize> Clone for ExtensionField<F, P, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: Copy, P, const K: usize> Copy for ExtensionField<F, P, K> {}
+
+impl<F: PartialEq, P, const K: usize> PartialEq for ExtensionField<F, P, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.coeffs == other.coeffs
+    }
+}
+
+impl<F: Eq, P, const K: usize> Eq for ExtensionField<F, P, K> {}
+
+impl<F: fmt::Debug, P, const K: usize> fmt::Debug for ExtensionField<F, P, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExtensionField({:?})", self.coeffs)
+    }
+}
+
+impl<F: fmt::Debug, P, const K: usize> fmt::Display for ExtensionField<F, P, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExtensionField({:?})", self.coeffs)
+    }
+}
+
+impl<F: PrimeField, P: ExtensionParams<F, K>, const K: usize> Field for ExtensionField<F, P, K> {
+    fn random<R: RngCore + ?Sized>(rng: &mut R) -> Self {
+        let mut coeffs = [F::zero(); K];
+        for c in coeffs.iter_mut() {
+            *c = F::random(rng);
+        }
+        Self::new(coeffs)
+    }
+
+    fn zero() -> Self {
+        Self::new([F::zero(); K])
+    }
+
+    fn one() -> Self {
+        let mut coeffs = [F::zero(); K];
+        coeffs[0] = F::one();
+        Self::new(coeffs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.coeffs.iter().all(|c| c.is_zero())
+    }
+
+    fn square(&mut self) {
+        let x = *self;
+        self.mul_assign(&x);
+    }
+
+    fn double(&mut self) {
+        for c in self.coeffs.iter_mut() {
+            c.double();
+        }
+    }
+
+    fn negate(&mut self) {
+        for c in self.coeffs.iter_mut() {
+            c.negate();
+        }
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        for (c, d) in self.coeffs.iter_mut().zip(&other.coeffs) {
+            c.add_assign(d);
+        }
+    }
+
+    fn sub_assign(&mut self, other: &Self) {
+        for (c, d) in self.coeffs.iter_mut().zip(&other.coeffs) {
+            c.sub_assign(d);
+        }
+    }
+
+    fn mul_assign(&mut self, other: &Self) {
+        // the product's coefficients of x^0, ..., x^(K-1) in low and of x^K, ..., x^(2K-2)
+        // in high, whose last entry stays zero
+        let mut low = [F::zero(); K];
+        let mut high = [F::zero(); K];
+        for (i, c) in self.coeffs.iter().enumerate() {
+            for (j, d) in other.coeffs.iter().enumerate() {
+                let mut cd = *c;
+                cd.mul_assign(d);
+                if i + j < K {
+                    low[i + j].add_assign(&cd);
+                } else {
+                    high[i + j - K].add_assign(&cd);
+                }
+            }
+        }
+
+        // x^K = -(m_0 + m_1*x + ... + m_(K-1)*x^(K-1)), reducing from the top
+        let modulus = P::modulus();
+        for i in (0..K - 1).rev() {
+            let top = high[i];
+            for (j, m) in modulus.iter().enumerate() {
+                let mut t = top;
+                t.mul_assign(m);
+                if i + j < K {
+                    low[i + j].sub_assign(&t);
+                } else {
+                    high[i + j - K].sub_assign(&t);
+                }
+            }
+        }
+        self.coeffs = low;
+    }
+
+    // x^(p^K - 2)
+    fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.pow(Self::order_minus(2)))
+        }
+    }
+
+    // x^(p^power)
+    fn frobenius_map(&mut self, power: usize) {
+        for _ in 0..power {
+            *self = self.pow(F::char());
+        }
+    }
+}
+
+impl<F: PrimeField, P: ExtensionParams<F, K>, const K: usize> FiniteField
+    for ExtensionField<F, P, K>
+{
+    const BYTES: usize = K * <F as FiniteField>::BYTES;
+
+    const SIZE_BITS: u32 = K as u32 * <F as FiniteField>::SIZE_BITS;
+
+    // Canonical encoding: the encodings of c_0, ..., c_(K-1) concatenated
+    fn encode(self, bytes: &mut [u8]) {
+        assert_eq!(bytes.len(), Self::BYTES);
+        for (chunk, c) in bytes.chunks_mut(F::BYTES).zip(&self.coeffs) {
+            c.encode(chunk);
+        }
+    }
+
+    // rejects bytes where any coefficient doesn't decode strictly
+    fn decode(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError> {
+        assert_eq!(bytes.len(), Self::BYTES);
+        let mut coeffs = [F::zero(); K];
+        for (c, chunk) in coeffs.iter_mut().zip(bytes.chunks(F::BYTES)) {
+            *c = F::decode(chunk)?;
+        }
+        return Ok(Self::new(coeffs));
+    }
+
+    fn subgroup_generator(n: u64) -> Option<Self> {
+        let exp = div_exact(&Self::order_minus(1), n)?;
+        return Some(Self::new(P::generator()).pow(&exp));
+    }
+
+    // samples every coefficient from its F::BYTES bytes
+    fn from_random_bytes(bytes: &mut [u8]) -> Option<Self> {
+        assert_eq!(bytes.len(), Self::BYTES);
+        let mut coeffs = [F::zero(); K];
+        for (c, chunk) in coeffs.iter_mut().zip(bytes.chunks_mut(F::BYTES)) {
+            *c = F::from_random_bytes(chunk)?;
+        }
+        return Some(Self::new(coeffs));
+    }
+}
+
+impl<F: PrimeField, P: ExtensionParams<F, K>, const K: usize> BatchField
+    for ExtensionField<F, P, K>
+{
+}
+
+impl<F: PrimeField, P: ExtensionParams<F, K>, const K: usize> OleField for ExtensionField<F, P, K> {
+    const A: usize = P::A;
+    const B: usize = P::B;
+
+    fn alpha() -> Self {
+        Self::new(P::alpha())
+    }
+
+    fn beta() -> Self {
+        Self::new(P::beta())
+    }
+
+    fn is_valid_field() -> bool {
+        Self::modulus_is_irreducible()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::params::OleParams;
+    use rand::thread_rng;
+
+    // Prime q = 4611686018312841983, with bit size 62
+    // q - 1 = 2 * 2305843009156420991, so GF(q) has no smooth subgroups, while
+    // q + 1 = 2^8 * 3^8 * 59 * 46536928561
+    // generator of multiplicative group: 5
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "4611686018312841983"]
+    #[PrimeFieldGenerator = "5"]
+    pub(crate) struct Fq(FqRepr);
+
+    // GF(q^2) = GF(q)[x]/(x^2 + 1), as q = 3 mod 4, with generator 1 + 2x
+    pub(crate) struct Fq2Params;
+
+    impl ExtensionParams<Fq, 2> for Fq2Params {
+        fn modulus() -> [Fq; 2] {
+            [Fq::one(), Fq::zero()]
+        }
+
+        fn generator() -> [Fq; 2] {
+            [fq(1), fq(2)]
+        }
+
+        const A: usize = 256;
+        const B: usize = 2187;
+
+        fn alpha() -> [Fq; 2] {
+            [fq(0x187fd7f54fd88ead), fq(0x2da05a06991dfb79)]
+        }
+
+        fn beta() -> [Fq; 2] {
+            [fq(0x18e050da96bc9fc8), fq(0x2dc1bdb58835568f)]
+        }
+    }
+
+    pub(crate) type Fq2 = ExtensionField<Fq, Fq2Params, 2>;
+
+    fn fq(x: u64) -> Fq {
+        Fq::from_repr(FqRepr::from(x)).unwrap()
+    }
+
+    #[test]
+    fn test_extension_arithmetic() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let a = Fq2::random(&mut rng);
+            let b = Fq2::random(&mut rng);
+            let c = Fq2::random(&mut rng);
+
+            // (a0 + a1*x)(b0 + b1*x) = a0*b0 - a1*b1 + (a0*b1 + a1*b0)*x
+            let [a0, a1] = *a.coeffs();
+            let [b0, b1] = *b.coeffs();
+            let mut re = a0;
+            re.mul_assign(&b0);
+            let mut t = a1;
+            t.mul_assign(&b1);
+            re.sub_assign(&t);
+            let mut im = a0;
+            im.mul_assign(&b1);
+            let mut t = a1;
+            t.mul_assign(&b0);
+            im.add_assign(&t);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            assert_eq!(ab, Fq2::new([re, im]));
+
+            // a*(b + c) = a*b + a*c
+            let mut lhs = b;
+            lhs.add_assign(&c);
+            lhs.mul_assign(&a);
+            let mut rhs = a;
+            rhs.mul_assign(&c);
+            rhs.add_assign(&ab);
+            assert_eq!(lhs, rhs);
+
+            let mut one = a.inverse().unwrap();
+            one.mul_assign(&a);
+            assert_eq!(one, Fq2::one());
+
+            // the frobenius map is conjugation, a0 - a1*x
+            let mut conj = a;
+            conj.frobenius_map(1);
+            let mut neg_a1 = a1;
+            neg_a1.negate();
+            assert_eq!(conj, Fq2::new([a0, neg_a1]));
+        }
+        assert!(Fq2::zero().inverse().is_none());
+    }
+
+    #[test]
+    fn test_extension_constants() {
+        assert_eq!(Fq2::BYTES, 16);
+        assert_eq!(Fq2::SIZE_BITS, 122);
+        assert!(Fq::subgroup_generator(4).is_none());
+        assert_eq!(Fq2::alpha().pow([Fq2::A as u64]), Fq2::one());
+        assert_ne!(Fq2::alpha().pow([(Fq2::A / 2) as u64]), Fq2::one());
+        assert_eq!(Fq2::beta().pow([Fq2::B as u64]), Fq2::one());
+        assert_ne!(Fq2::beta().pow([(Fq2::B / 3) as u64]), Fq2::one());
+        assert!(Fq2::subgroup_generator(3u64.pow(9)).is_none());
+        assert_eq!(
+            Fq2::alpha(),
+            Fq2::subgroup_generator(Fq2::A as u64).unwrap()
+        );
+        assert_eq!(Fq2::beta(), Fq2::subgroup_generator(Fq2::B as u64).unwrap());
+    }
+
+    // x^2 - 1 = (x - 1)(x + 1), for checking that the irreducibility test rejects it.
+    // Only the modulus is used
+    struct ReducibleParams;
+
+    impl ExtensionParams<Fq, 2> for ReducibleParams {
+        fn modulus() -> [Fq; 2] {
+            let mut m0 = Fq::one();
+            m0.negate();
+            [m0, Fq::zero()]
+        }
+
+        fn generator() -> [Fq; 2] {
+            [fq(1), fq(1)]
+        }
+
+        const A: usize = 2;
+        const B: usize = 3;
+
+        fn alpha() -> [Fq; 2] {
+            [Fq::zero(), Fq::one()]
+        }
+
+        fn beta() -> [Fq; 2] {
+            [Fq::zero(), Fq::one()]
+        }
+    }
+
+    #[test]
+    fn test_extension_modulus() {
+        assert!(Fq2::modulus_is_irreducible());
+        assert!(!ExtensionField::<Fq, ReducibleParams, 2>::modulus_is_irreducible());
+
+        // OLE parameters can't be built over a reducible modulus
+        assert!(Fq2::is_valid_field());
+        let reducible =
+            std::panic::catch_unwind(OleParams::<ExtensionField<Fq, ReducibleParams, 2>>::default);
+        assert!(reducible.is_err());
+    }
+
+    #[test]
+    fn test_extension_encoding() {
+        let mut rng = thread_rng();
+        let mut bytes = [0u8; 16];
+        for _ in 0..100 {
+            let v = Fq2::random(&mut rng);
+            v.encode(&mut bytes);
+            assert_eq!(Fq2::decode(&bytes).unwrap(), v);
+        }
+
+        // q is rejected in either coefficient
+        let q = Fq::char().0[0].to_le_bytes();
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&q);
+        assert!(Fq2::decode(&bytes).is_err());
+        let mut bytes = [0u8; 16];
+        bytes[8..].copy_from_slice(&q);
+        assert!(Fq2::decode(&bytes).is_err());
+    }
+}
@@ -1,7 +1,31 @@
-use ff::{Field, PrimeField};
+use crate::field::BatchField;
+use ff::Field;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// With the parallel feature, the butterflies of layers on at least PARALLEL_MIN_LEN
+// elements are spread over threads, splitting blocks of more than PARALLEL_CHUNK
+// butterflies into columns
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_LEN: usize = 1 << 12;
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK: usize = 1 << 9;
+
+// FFTs of at least SIX_STEP_MIN_LEN elements, on an EvaluationDomain or by fft2_in_place
+// and fft3_in_place, run as two rounds of FFTs of about the square root of the size,
+// which fit in cache, instead of layers across all the data
+const SIX_STEP_MIN_LEN: usize = 1 << 16;
+
+// fft_batch interleaves at least BATCH_MIN_WIDTH vectors, below that the butterflies on
+// short rows cost more than transforming the vectors one by one
+const BATCH_MIN_WIDTH: usize = 4;
+
+// The first radix 4 or 9 layer, on blocks of 4 or 9 values, gathers the multiplications
+// of SMALL_BLOCKS blocks into each batch
+const SMALL_BLOCKS: usize = 64;
 
 // Slow reference implementation, use fft2_in_place
-pub fn fft2<F: PrimeField>(a_coeffs: &[F], alpha: &F) -> Vec<F> {
+pub fn fft2<F: Field>(a_coeffs: &[F], alpha: &F) -> Vec<F> {
     let l = a_coeffs.len();
 
     if l == 1 {
@@ -9,11 +33,11 @@ pub fn fft2<F: PrimeField>(a_coeffs: &[F], alpha: &F) -> Vec<F> {
     }
 
     // split A into B and C such that A(x) = B(x^2) + x * C(x^2)
-    let b_coeffs: Vec<F> = a_coeffs.iter().step_by(2).map(|x| *x).collect();
-    let c_coeffs: Vec<F> = a_coeffs.iter().skip(1).step_by(2).map(|x| *x).collect();
+    let b_coeffs: Vec<F> = a_coeffs.iter().step_by(2).copied().collect();
+    let c_coeffs: Vec<F> = a_coeffs.iter().skip(1).step_by(2).copied().collect();
 
     // apply recursively
-    let mut alpha_2 = alpha.clone();
+    let mut alpha_2 = *alpha;
     alpha_2.square();
     let b_values = fft2(&b_coeffs, &alpha_2);
     let c_values = fft2(&c_coeffs, &alpha_2);
@@ -21,13 +45,13 @@ pub fn fft2<F: PrimeField>(a_coeffs: &[F], alpha: &F) -> Vec<F> {
     // combine subresults
     let mut a_values = vec![F::zero(); l];
     for i in 0..(l / 2) {
-        let x = alpha.pow(&[i as u64]);
+        let x = alpha.pow([i as u64]);
         a_values[i] = c_values[i];
         a_values[i].mul_assign(&x);
         a_values[i].add_assign(&b_values[i]);
 
         let j = i + l / 2;
-        let x = alpha.pow(&[j as u64]);
+        let x = alpha.pow([j as u64]);
         a_values[j] = c_values[i];
         a_values[j].mul_assign(&x);
         a_values[j].add_assign(&b_values[i]);
@@ -37,19 +61,19 @@ pub fn fft2<F: PrimeField>(a_coeffs: &[F], alpha: &F) -> Vec<F> {
 }
 
 // Slow reference implementation, use fft3_in_place
-pub fn fft3<F: PrimeField>(coeffs: &[F], beta: &F) -> Vec<F> {
+pub fn fft3<F: Field>(coeffs: &[F], beta: &F) -> Vec<F> {
     let l = coeffs.len();
     if l == 1 {
         return coeffs.to_vec();
     }
 
-    let a_coeffs: Vec<F> = coeffs.iter().step_by(3).map(|x| *x).collect();
-    let b_coeffs: Vec<F> = coeffs.iter().skip(1).step_by(3).map(|x| *x).collect();
-    let c_coeffs: Vec<F> = coeffs.iter().skip(2).step_by(3).map(|x| *x).collect();
+    let a_coeffs: Vec<F> = coeffs.iter().step_by(3).copied().collect();
+    let b_coeffs: Vec<F> = coeffs.iter().skip(1).step_by(3).copied().collect();
+    let c_coeffs: Vec<F> = coeffs.iter().skip(2).step_by(3).copied().collect();
 
-    let mut beta3 = beta.clone();
+    let mut beta3 = *beta;
     beta3.square();
-    beta3.mul_assign(&beta);
+    beta3.mul_assign(beta);
 
     let a_vals = fft3(&a_coeffs, &beta3);
     let b_vals = fft3(&b_coeffs, &beta3);
@@ -58,8 +82,8 @@ pub fn fft3<F: PrimeField>(coeffs: &[F], beta: &F) -> Vec<F> {
     let mut result = vec![F::zero(); l]; // could use unsafe unitialized arrays
     for i in 0..(l / 3) {
         let mut f = |j| {
-            let x = beta.pow(&[j as u64]);
-            let x2 = x.pow(&[2 as u64]);
+            let x = beta.pow([j as u64]);
+            let x2 = x.pow([2u64]);
             let mut v = c_vals[i];
             v.mul_assign(&x2);
             let mut bx = b_vals[i];
@@ -76,93 +100,900 @@ pub fn fft3<F: PrimeField>(coeffs: &[F], beta: &F) -> Vec<F> {
     return result;
 }
 
-// only works when coeffs.len() = 2^k for some k, and alpha is a 2^k'th rooth of unity
-pub fn fft2_in_place<F: PrimeField>(coeffs: &mut [F], alpha: &F) {
-    digit_reverse_swap(coeffs, 2);
+// only works when coeffs.len() = 2^k for some k, and alpha is a 2^k'th rooth of unity.
+// From SIX_STEP_MIN_LEN on it runs the six-step FFT, whose rounds fit in cache, and
+// below that the butterfly layers, computing the twiddles on the way. Repeated FFTs are
+// cheaper on an EvaluationDomain built once
+pub fn fft2_in_place<F: BatchField>(coeffs: &mut [F], alpha: &F) {
+    if coeffs.len() >= SIX_STEP_MIN_LEN {
+        return six_step_fft(coeffs, alpha);
+    }
+    fft_layers(coeffs, alpha, 2);
+}
+
+// as fft2_in_place, for coeffs.len() = 3^k and beta a 3^k'th root of unity
+pub fn fft3_in_place<F: BatchField>(coeffs: &mut [F], beta: &F) {
+    if coeffs.len() >= SIX_STEP_MIN_LEN {
+        return six_step_fft(coeffs, beta);
+    }
+    fft_layers(coeffs, beta, 3);
+}
+
+// the butterfly layers of fft2_in_place and fft3_in_place, for a length that is a power
+// of radix
+fn fft_layers<F: BatchField>(coeffs: &mut [F], root: &F, radix: usize) {
+    digit_reverse_swap(coeffs, radix);
     let n = coeffs.len();
-    let mut distance = 1usize;
-    while distance < n {
-        let mut factor = F::one();
-        let factor_multiplier = alpha.pow([(n / distance / 2) as u64]);
-        for k in 0..distance {
-            for j in (0..n).step_by(2 * distance) {
-                let mut x = coeffs[j + k];
-                let mut y = coeffs[j + k + distance];
-                y.mul_assign(&factor);
-
-                coeffs[j + k].add_assign(&y);
-                x.sub_assign(&y);
-                coeffs[j + k + distance] = x;
+    let (a, b) = smooth_exponents(n);
+    run_butterfly_layers(coeffs, a, b, 1, 0, n, |order, len| {
+        let mut twiddles = Vec::with_capacity(len);
+        twiddle_factors(&mut twiddles, &root.pow([(n / order) as u64]), len);
+        twiddles
+    });
+}
+
+// a six-step FFT built for a single transform, only computing the powers of root the
+// twiddles are taken from and the two small domains
+fn six_step_fft<F: BatchField>(coeffs: &mut [F], root: &F) {
+    let n = coeffs.len();
+    let mut powers = Vec::with_capacity(n);
+    twiddle_factors(&mut powers, root, n);
+    SixStep::new(n, &powers).fft(coeffs, &powers);
+}
+
+// fft2_in_place of each of the vectors in one go, for vectors of length 2^k and alpha of
+// order 2^k, see EvaluationDomain::fft_batch
+pub fn fft2_batch<F: BatchField>(vectors: &mut [Vec<F>], alpha: &F) {
+    if let Some(domain) = batch_domain(vectors, alpha, 2) {
+        domain.fft_batch(vectors);
+    }
+}
+
+// fft3_in_place of each of the vectors in one go, for vectors of length 3^k and beta of
+// order 3^k, see EvaluationDomain::fft_batch
+pub fn fft3_batch<F: BatchField>(vectors: &mut [Vec<F>], beta: &F) {
+    if let Some(domain) = batch_domain(vectors, beta, 3) {
+        domain.fft_batch(vectors);
+    }
+}
+
+// Inverse of fft2_batch
+pub fn fft2_batch_inverse<F: BatchField>(vectors: &mut [Vec<F>], alpha: &F) {
+    if let Some(domain) = batch_domain(vectors, alpha, 2) {
+        domain.fft_batch_inverse(vectors);
+    }
+}
+
+// Inverse of fft3_batch
+pub fn fft3_batch_inverse<F: BatchField>(vectors: &mut [Vec<F>], beta: &F) {
+    if let Some(domain) = batch_domain(vectors, beta, 3) {
+        domain.fft_batch_inverse(vectors);
+    }
+}
+
+// the domain generated by root for the length of the vectors, a power of radix, or None
+// without vectors
+fn batch_domain<F: BatchField>(
+    vectors: &[Vec<F>],
+    root: &F,
+    radix: usize,
+) -> Option<EvaluationDomain<F>> {
+    let n = vectors.first()?.len();
+    let (a, b) = smooth_exponents(n);
+    assert!(
+        (radix == 2 && b == 0) || (radix == 3 && a == 0),
+        "vectors of length {} are not a power of {}",
+        n,
+        radix
+    );
+    return Some(EvaluationDomain::new(n, *root));
+}
+
+// The multiplicative subgroup generated by an element of order size = 2^a * 3^b, with
+// the powers of the generator, the inverse of size and the input permutation of the FFT
+// computed once
+#[derive(Clone, Debug)]
+pub struct EvaluationDomain<F: BatchField> {
+    size: usize,
+    radix2_layers: usize, // a
+    radix3_layers: usize, // b
+    generator: F,
+    size_inv: F,
+    powers: Vec<F>,                    // generator^i for 0 <= i < size
+    permutation: Vec<usize>,           // position of each input before the butterfly layers
+    six_step: Option<Box<SixStep<F>>>, // for sizes of at least SIX_STEP_MIN_LEN
+}
+
+// The factorization size = n1 * n2 of a six-step FFT
+#[derive(Clone, Debug)]
+struct SixStep<F: BatchField> {
+    outer: EvaluationDomain<F>, // order n1, generated by generator^n2
+    inner: EvaluationDomain<F>, // order n2, generated by generator^n1
+}
+
+impl<F: BatchField> EvaluationDomain<F> {
+    // generator must have order exactly size
+    pub fn new(size: usize, generator: F) -> Self {
+        let (a, b) = smooth_exponents(size);
+        let mut powers = Vec::with_capacity(size);
+        twiddle_factors(&mut powers, &generator, size);
+        let mut last = powers[size - 1];
+        last.mul_assign(&generator);
+        assert_eq!(last, F::one());
+        assert!(a == 0 || powers[size / 2] != F::one());
+        assert!(b == 0 || powers[size / 3] != F::one());
+
+        let six_step = if size >= SIX_STEP_MIN_LEN {
+            Some(Box::new(SixStep::new(size, &powers)))
+        } else {
+            None
+        };
+
+        Self {
+            size: size,
+            radix2_layers: a,
+            radix3_layers: b,
+            generator: generator,
+            size_inv: from_usize::<F>(size).inverse().unwrap(),
+            powers: powers,
+            permutation: mixed_radix_table(size, a, b),
+            six_step: six_step,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn generator(&self) -> F {
+        self.generator
+    }
+
+    // generator^i
+    pub fn element(&self, i: usize) -> F {
+        self.powers[i % self.size]
+    }
+
+    // generator^0, generator^1, ..., generator^(size-1)
+    pub fn elements(&self) -> impl Iterator<Item = F> + '_ {
+        self.powers.iter().copied()
+    }
+
+    // Takes the size coefficients of f, afterwards coeffs[i] = f(generator^i)
+    pub fn fft(&self, coeffs: &mut [F]) {
+        assert_eq!(coeffs.len(), self.size);
+        if let Some(six_step) = &self.six_step {
+            return six_step.fft(coeffs, &self.powers);
+        }
+        let input = coeffs.to_vec();
+        for (x, pos) in input.into_iter().zip(&self.permutation) {
+            coeffs[*pos] = x;
+        }
+        self.butterfly_layers(coeffs, 1, 0, self.size);
+    }
+
+    // fft of the polynomial with the given leading coefficients, and zeros up to size,
+    // returning only its first outputs points. Skips the first layers while they see a
+    // single non-zero input per block, and the butterflies not leading to the first
+    // outputs points
+    pub fn fft_pruned(&self, coeffs: &[F], outputs: usize) -> Vec<F> {
+        assert!(coeffs.len() <= self.size);
+        assert!(outputs <= self.size);
+        let mut values = vec![F::zero(); self.size];
+        for (x, pos) in coeffs.iter().zip(&self.permutation) {
+            values[*pos] = *x;
+        }
+
+        // inputs below size / block are placed at the multiples of block, and the
+        // skipped layers take each block (x, 0, ..., 0) to (x, x, ..., x)
+        let mut skipped = 0;
+        let mut block = 1;
+        while skipped < self.radix2_layers + self.radix3_layers {
+            let radix = self.radix(skipped);
+            if coeffs.len() > self.size / (block * radix) {
+                break;
+            }
+            block *= radix;
+            skipped += 1;
+        }
+        for chunk in values.chunks_mut(block) {
+            let x = chunk[0];
+            for y in chunk[1..].iter_mut() {
+                *y = x;
             }
-            factor.mul_assign(&factor_multiplier);
         }
-        distance <<= 1;
+
+        self.butterfly_layers(&mut values, 1, skipped, outputs);
+        values.truncate(outputs);
+        return values;
+    }
+
+    // radix of the given butterfly layer, the radix 2 layers come first
+    fn radix(&self, layer: usize) -> usize {
+        if layer < self.radix2_layers {
+            2
+        } else {
+            3
+        }
+    }
+
+    // Runs the butterfly layers from first_layer on, on the permuted inputs, which are
+    // rows of width values. Only the butterflies in the first columns of each block are
+    // computed, which are the ones leading to the first columns outputs
+    fn butterfly_layers(&self, values: &mut [F], width: usize, first_layer: usize, columns: usize) {
+        // the twiddles of each layer are powers of generator at a stride
+        let n = self.size;
+        run_butterfly_layers(
+            values,
+            self.radix2_layers,
+            self.radix3_layers,
+            width,
+            first_layer,
+            columns,
+            |order, len| {
+                self.powers
+                    .iter()
+                    .step_by(n / order)
+                    .take(len)
+                    .copied()
+                    .collect()
+            },
+        );
+    }
+
+    // Inverse of fft. Evaluating at the inverse powers of generator reverses all but the
+    // first point, so this is fft followed by the reversal and scaling by 1/size
+    pub fn fft_inverse(&self, points: &mut [F]) {
+        self.fft(points);
+        points[1..].reverse();
+        F::batch_scale(points, &self.size_inv);
+    }
+
+    // fft of each of the vectors, all of length size. The vectors are interleaved, so
+    // each butterfly runs on the values of all of them at once with the same twiddle,
+    // computed once for all of them. Fewer than BATCH_MIN_WIDTH vectors, and vectors
+    // from SIX_STEP_MIN_LEN on, which go through the six-step FFT, are transformed one
+    // by one
+    pub fn fft_batch(&self, vectors: &mut [Vec<F>]) {
+        for v in vectors.iter() {
+            assert_eq!(v.len(), self.size);
+        }
+        let width = vectors.len();
+        if width < BATCH_MIN_WIDTH || self.six_step.is_some() {
+            for v in vectors.iter_mut() {
+                self.fft(v);
+            }
+            return;
+        }
+
+        // row i holds the i'th values of all vectors, in the permuted order
+        let mut values = vec![F::zero(); self.size * width];
+        for (i, pos) in self.permutation.iter().enumerate() {
+            let row = &mut values[*pos * width..(*pos + 1) * width];
+            for (x, v) in row.iter_mut().zip(vectors.iter()) {
+                *x = v[i];
+            }
+        }
+        self.butterfly_layers(&mut values, width, 0, self.size);
+        for (i, row) in values.chunks(width).enumerate() {
+            for (v, x) in vectors.iter_mut().zip(row) {
+                v[i] = *x;
+            }
+        }
+    }
+
+    // Inverse of fft_batch, as fft_inverse
+    pub fn fft_batch_inverse(&self, points: &mut [Vec<F>]) {
+        self.fft_batch(points);
+        for v in points.iter_mut() {
+            v[1..].reverse();
+            F::batch_scale(v, &self.size_inv);
+        }
+    }
+
+    // Takes the size coefficients of f, afterwards coeffs[i] = f(shift * generator^i)
+    pub fn coset_fft(&self, coeffs: &mut [F], shift: &F) {
+        let mut shift_powers = Vec::with_capacity(self.size);
+        twiddle_factors(&mut shift_powers, shift, coeffs.len());
+        F::batch_mul(coeffs, &shift_powers);
+        self.fft(coeffs);
+    }
+
+    // Inverse of coset_fft
+    pub fn coset_fft_inverse(&self, points: &mut [F], shift: &F) {
+        self.fft_inverse(points);
+        let mut shift_powers = Vec::with_capacity(self.size);
+        twiddle_factors(&mut shift_powers, &shift.inverse().unwrap(), points.len());
+        F::batch_mul(points, &shift_powers);
     }
 }
 
-pub fn fft3_in_place<F: PrimeField>(coeffs: &mut [F], beta: &F) {
-    digit_reverse_swap(coeffs, 3);
-    let n = coeffs.len();
+impl<F: BatchField> SixStep<F> {
+    // the factorization into n1 of about the square root of size, and n2, from the
+    // powers of an element of order size
+    fn new(size: usize, powers: &[F]) -> Self {
+        let (a, b) = smooth_exponents(size);
+        let n1 = 2usize.pow((a / 2) as u32) * 3usize.pow((b / 2) as u32);
+        let n2 = size / n1;
+        Self {
+            outer: EvaluationDomain::new(n1, powers[n2]),
+            inner: EvaluationDomain::new(n2, powers[n1]),
+        }
+    }
 
-    let beta_1 = beta.pow([(n / 3) as u64]);
-    let mut beta_2 = beta_1;
-    beta_2.square();
+    // With input j1 + n1*j2 and output k2 + n2*k1, the output is the FFTs of size n1
+    // over j1 of the FFTs of size n2 over j2 of the inputs at each j1, times
+    // generator^(j1*k2), taken from the powers of generator of the whole domain at a
+    // stride of j1. Transposing between the two rounds keeps each FFT on contiguous data
+    fn fft(&self, coeffs: &mut [F], powers: &[F]) {
+        let (n1, n2) = (self.outer.size, self.inner.size);
+        let mut buffer = vec![F::zero(); n1 * n2];
+        transpose(coeffs, &mut buffer, n2, n1);
+        for_each_row(&mut buffer, n2, |j1, row| {
+            self.inner.fft(row);
+            if j1 > 0 {
+                let twiddles: Vec<F> = powers.iter().step_by(j1).take(n2).copied().collect();
+                F::batch_mul(row, &twiddles);
+            }
+        });
+        transpose(&buffer, coeffs, n1, n2);
+        for_each_row(coeffs, n1, |_, row| self.outer.fft(row));
+        transpose(coeffs, &mut buffer, n2, n1);
+        coeffs.copy_from_slice(&buffer);
+    }
+}
 
+// (a, b) such that n = 2^a * 3^b
+fn smooth_exponents(n: usize) -> (usize, usize) {
+    assert_ne!(n, 0);
+    let a = n.trailing_zeros() as usize;
+    let mut m = n >> a;
+    let mut b = 0;
+    while m.is_multiple_of(3) {
+        m /= 3;
+        b += 1;
+    }
+    if m != 1 {
+        panic!(
+            "Cant do mixed-radix FFT. Data size {} is not on the form 2^a * 3^b",
+            n
+        );
+    }
+    return (a, b);
+}
+
+// Position of each input before a radix 2 layers followed by b radix 3 layers: the last
+// layer combines the sub-FFTs of the inputs at each residue modulo 3, placed one after
+// another, and so on recursively. So the table of size m * r, for the radix r of the
+// last layer, places i at (i % r) * m plus the position of i / r in the table of size m
+fn mixed_radix_table(n: usize, a: usize, b: usize) -> Vec<usize> {
+    let mut table = vec![0usize];
+    for radix in std::iter::repeat_n(2, a).chain(std::iter::repeat_n(3, b)) {
+        let m = table.len();
+        table = (0..m * radix)
+            .map(|i| (i % radix) * m + table[i / radix])
+            .collect();
+    }
+    assert_eq!(table.len(), n);
+    return table;
+}
+
+// dst = the transpose of src, a rows x cols matrix in row-major order, copied in tiles
+// that fit in cache
+fn transpose<T: Copy>(src: &[T], dst: &mut [T], rows: usize, cols: usize) {
+    const TILE: usize = 32;
+    for i0 in (0..rows).step_by(TILE) {
+        for j0 in (0..cols).step_by(TILE) {
+            for i in i0..std::cmp::min(i0 + TILE, rows) {
+                for j in j0..std::cmp::min(j0 + TILE, cols) {
+                    dst[j * rows + i] = src[i * cols + j];
+                }
+            }
+        }
+    }
+}
+
+// calls f on the index and contents of each row of length len, concurrently with the
+// parallel feature
+fn for_each_row<F: BatchField, G: Fn(usize, &mut [F]) + Sync + Send>(
+    data: &mut [F],
+    len: usize,
+    f: G,
+) {
+    #[cfg(feature = "parallel")]
+    {
+        data.par_chunks_mut(len)
+            .enumerate()
+            .for_each(|(i, row)| f(i, row));
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        data.chunks_mut(len)
+            .enumerate()
+            .for_each(|(i, row)| f(i, row));
+    }
+}
+
+// Runs the butterfly layers of an FFT of size 2^a * 3^b on the permuted values, radix 2
+// layers first, from first_layer on. Consecutive layers of the same radix go in pairs
+// through the radix 4 and radix 9 kernels, an odd one left over through radix2_layer or
+// radix3_layer. roots(order, len) gives the first len powers of the root of unity of
+// the given order in the transform. Only the butterflies in the first columns of each
+// block are computed.
+//
+// The values are rows of width elements, the entries of width interleaved FFTs, and
+// each butterfly runs on whole rows with a single twiddle
+fn run_butterfly_layers<F: BatchField, R: Fn(usize, usize) -> Vec<F>>(
+    values: &mut [F],
+    a: usize,
+    b: usize,
+    width: usize,
+    first_layer: usize,
+    columns: usize,
+    roots: R,
+) {
+    let beta = if b > 0 { roots(3, 2)[1] } else { F::one() };
+    let mut layer = 0;
     let mut distance = 1usize;
-    while distance < n {
-        let mut factor = F::one();
-        let factor_multiplier = beta.pow([(n / distance / 3) as u64]);
-        for k in 0..distance {
-            let mut factor2 = factor;
-            factor2.square();
-            for j in (0..n).step_by(3 * distance) {
-                let x = coeffs[j + k];
-                let mut y = coeffs[j + k + distance];
-                let mut z = coeffs[j + k + 2 * distance];
-                y.mul_assign(&factor);
-                z.mul_assign(&factor2);
-
-                coeffs[j + k].add_assign(&y);
-                coeffs[j + k].add_assign(&z);
-
-                for i in 1..3 {
-                    y.mul_assign(&beta_1);
-                    z.mul_assign(&beta_2);
-                    coeffs[j + k + i * distance] = x;
-                    coeffs[j + k + i * distance].add_assign(&y);
-                    coeffs[j + k + i * distance].add_assign(&z);
+    while layer < a + b {
+        let (radix, end) = if layer < a { (2, a) } else { (3, a + b) };
+        if layer < first_layer {
+            layer += 1;
+            distance *= radix;
+            continue;
+        }
+
+        let columns = std::cmp::min(columns, distance);
+        let stride = distance * width;
+        if layer + 1 < end && fuse_layers(values.len(), stride) {
+            if distance == 1 && columns == 1 {
+                // the first pair, whose inner butterflies have no twiddles
+                let w = roots(radix * radix, 2 * radix - 1);
+                if radix == 2 {
+                    radix4_first_layers(values, width, &w[1]);
+                } else {
+                    radix9_first_layers(values, width, &w, &beta);
                 }
+            } else if radix == 2 {
+                let inner = roots(2 * distance, columns);
+                let outer = roots(4 * distance, 2 * distance);
+                radix4_layer(values, stride, width, &inner, &outer);
+            } else {
+                let (inner, inner2) = with_squares(roots(3 * distance, 2 * columns), columns);
+                let (outer, outer2) = with_squares(roots(9 * distance, 6 * distance), 3 * distance);
+                radix9_layer(
+                    values,
+                    stride,
+                    width,
+                    [&inner, &inner2],
+                    [&outer, &outer2],
+                    &beta,
+                );
+            }
+            layer += 2;
+            distance *= radix * radix;
+        } else {
+            if radix == 2 {
+                radix2_layer(values, stride, width, &roots(2 * distance, columns));
+            } else {
+                let (twiddles, twiddles2) = with_squares(roots(3 * distance, 2 * columns), columns);
+                radix3_layer(values, stride, width, &twiddles, &twiddles2, &beta);
+            }
+            layer += 1;
+            distance *= radix;
+        }
+    }
+}
+
+// w^k and w^(2k) for k < len, from the powers w^k for k < 2 * len
+fn with_squares<F: Copy>(powers: Vec<F>, len: usize) -> (Vec<F>, Vec<F>) {
+    let squares = powers.iter().step_by(2).take(len).copied().collect();
+    let mut powers = powers;
+    powers.truncate(len);
+    return (powers, squares);
+}
+
+// Whether the layers at distance and radix * distance go through a single radix^2
+// kernel. Those only run in parallel over the blocks, so with the parallel feature large
+// inputs with few blocks keep to the single layers, which split the blocks into columns
+#[cfg(feature = "parallel")]
+fn fuse_layers(len: usize, distance: usize) -> bool {
+    len < PARALLEL_MIN_LEN || distance < PARALLEL_CHUNK
+}
+
+#[cfg(not(feature = "parallel"))]
+fn fuse_layers(_len: usize, _distance: usize) -> bool {
+    true
+}
+
+// Calls f on each block of block_len values with a scratch of scratch_len elements,
+// with the parallel feature on all cores for large inputs
+fn for_each_block<F: BatchField, G: Fn(&mut [F], &mut [F]) + Sync + Send>(
+    coeffs: &mut [F],
+    block_len: usize,
+    scratch_len: usize,
+    f: G,
+) {
+    #[cfg(feature = "parallel")]
+    {
+        if coeffs.len() >= PARALLEL_MIN_LEN {
+            coeffs.par_chunks_mut(block_len).for_each_init(
+                || vec![F::zero(); scratch_len],
+                |scratch, block| f(block, scratch),
+            );
+            return;
+        }
+    }
+
+    let mut scratch = vec![F::zero(); scratch_len];
+    for block in coeffs.chunks_mut(block_len) {
+        f(block, &mut scratch);
+    }
+}
+
+// Combines the sub-FFTs of size distance into sub-FFTs of twice the size, where
+// twiddles are the first powers of a root of unity of order 2 * distance / width, one
+// for each row of width values. Only the first twiddles.len() rows of butterflies in
+// each block are computed
+fn radix2_layer<F: BatchField>(coeffs: &mut [F], distance: usize, width: usize, twiddles: &[F]) {
+    let columns = twiddles.len() * width;
+    #[cfg(feature = "parallel")]
+    {
+        if coeffs.len() >= PARALLEL_MIN_LEN && columns >= PARALLEL_CHUNK {
+            // few large blocks, split each into columns instead
+            let rows = std::cmp::max(PARALLEL_CHUNK / width, 1);
+            for block in coeffs.chunks_mut(2 * distance) {
+                let (x, y) = halves(block, distance, columns);
+                x.par_chunks_mut(rows * width)
+                    .zip(y.par_chunks_mut(rows * width))
+                    .zip(twiddles.par_chunks(rows))
+                    .for_each_init(
+                        || vec![F::zero(); rows * width],
+                        |wy, ((x, y), w)| radix2_butterflies(x, y, w, &mut wy[..x.len()]),
+                    );
             }
-            factor.mul_assign(&factor_multiplier);
+            return;
         }
-        distance = distance * 3;
+    }
+
+    for_each_block(coeffs, 2 * distance, columns, |block, wy| {
+        let (x, y) = halves(block, distance, columns);
+        radix2_butterflies(x, y, twiddles, wy);
+    });
+}
+
+// Two radix 2 layers in one pass, combining the sub-FFTs of size distance into sub-FFTs
+// of four times the size, on rows of width values. With d = distance / width, inner are
+// the first powers of a root of unity w^2 of order 2 * d, and outer the powers w^k for
+// k < 2 * d. Each block of 4 * distance values goes through the layer at distance on
+// both its halves and then through the one at 2 * distance while it is in cache. Only
+// the first inner.len() rows of butterflies in each quarter are computed
+fn radix4_layer<F: BatchField>(
+    coeffs: &mut [F],
+    distance: usize,
+    width: usize,
+    inner: &[F],
+    outer: &[F],
+) {
+    let columns = inner.len() * width;
+    let d = distance / width;
+    for_each_block(coeffs, 4 * distance, columns, |block, wy| {
+        for half in block.chunks_mut(2 * distance) {
+            let (x, y) = halves(half, distance, columns);
+            radix2_butterflies(x, y, inner, wy);
+        }
+        // the outer butterflies of the second quarter use the twiddles w^distance * w^k,
+        // with w^distance a fourth root of unity
+        let (x, y) = block.split_at_mut(2 * distance);
+        for quarter in 0..2 {
+            let range = quarter * distance..quarter * distance + columns;
+            let twiddles = &outer[quarter * d..quarter * d + inner.len()];
+            radix2_butterflies(&mut x[range.clone()], &mut y[range], twiddles, wy);
+        }
+    });
+}
+
+// The first two radix 2 layers, on blocks of 4 rows of width values. The only twiddle
+// besides 1 is the fourth root of unity iota, so one multiplication per 4 values
+// instead of four. Single values are transposed in chunks of SMALL_BLOCKS blocks, so
+// that the butterflies run on the vectors of the i'th values of all blocks in a chunk
+fn radix4_first_layers<F: BatchField>(coeffs: &mut [F], width: usize, iota: &F) {
+    if width > 1 {
+        return for_each_block(coeffs, 4 * width, width, |block, t| {
+            radix4_first_butterflies(block, width, iota, t)
+        });
+    }
+    for_each_block(
+        coeffs,
+        4 * SMALL_BLOCKS,
+        5 * SMALL_BLOCKS,
+        |chunk, scratch| {
+            let blocks = chunk.len() / 4;
+            let (values, t) = scratch[..5 * blocks].split_at_mut(4 * blocks);
+            transpose(chunk, values, blocks, 4);
+            radix4_first_butterflies(values, blocks, iota, t);
+            transpose(values, chunk, 4, blocks);
+        },
+    );
+}
+
+// the butterflies of radix4_first_layers on values, 4 vectors of length len, using the
+// scratch t
+fn radix4_first_butterflies<F: BatchField>(values: &mut [F], len: usize, iota: &F, t: &mut [F]) {
+    for half in values.chunks_mut(2 * len) {
+        let (x, y) = half.split_at_mut(len);
+        dft2(x, y, t);
+    }
+    F::batch_scale(&mut values[3 * len..], iota);
+    let (x, y) = values.split_at_mut(2 * len);
+    for quarter in 0..2 {
+        let range = quarter * len..(quarter + 1) * len;
+        dft2(&mut x[range.clone()], &mut y[range], t);
     }
 }
 
-// TODO: dont use from_str hack
-pub fn fft3_inverse<F: Field + PrimeField>(points: &mut [F], beta: &F) {
-    let len_inv = F::from_str(&points.len().to_string())
-        .unwrap()
-        .inverse()
-        .unwrap();
+// the first columns of the halves of a block
+fn halves<F>(block: &mut [F], distance: usize, columns: usize) -> (&mut [F], &mut [F]) {
+    let (x, y) = block.split_at_mut(distance);
+    return (&mut x[..columns], &mut y[..columns]);
+}
+
+// butterflies on the halves (x, y) of a block: x + w*y, x - w*y, using the scratch wy
+fn radix2_butterflies<F: BatchField>(x: &mut [F], y: &mut [F], twiddles: &[F], wy: &mut [F]) {
+    wy.copy_from_slice(y);
+    mul_twiddles(wy, twiddles);
+    y.copy_from_slice(x);
+    F::batch_sub(y, wy);
+    F::batch_add(x, wy);
+}
+
+// butterflies without twiddles: x + y, x - y, using the scratch t
+fn dft2<F: BatchField>(x: &mut [F], y: &mut [F], t: &mut [F]) {
+    t.copy_from_slice(y);
+    y.copy_from_slice(x);
+    F::batch_sub(y, t);
+    F::batch_add(x, t);
+}
+
+// Combines the sub-FFTs of size distance into sub-FFTs of three times the size, where
+// twiddles are the first powers w^k of a root of unity of order 3 * distance / width,
+// one for each row of width values, twiddles2 their squares and beta the cube root of
+// unity w^(distance / width). Only the first twiddles.len() rows of butterflies in each
+// block are computed
+fn radix3_layer<F: BatchField>(
+    coeffs: &mut [F],
+    distance: usize,
+    width: usize,
+    twiddles: &[F],
+    twiddles2: &[F],
+    beta: &F,
+) {
+    let columns = twiddles.len() * width;
+    #[cfg(feature = "parallel")]
+    {
+        if coeffs.len() >= PARALLEL_MIN_LEN && columns >= PARALLEL_CHUNK {
+            // few large blocks, split each into columns instead
+            let rows = std::cmp::max(PARALLEL_CHUNK / width, 1);
+            for block in coeffs.chunks_mut(3 * distance) {
+                let (x, y, z) = thirds(block, distance, columns);
+                x.par_chunks_mut(rows * width)
+                    .zip(y.par_chunks_mut(rows * width))
+                    .zip(z.par_chunks_mut(rows * width))
+                    .zip(twiddles.par_chunks(rows))
+                    .zip(twiddles2.par_chunks(rows))
+                    .for_each_init(
+                        || vec![F::zero(); 2 * rows * width],
+                        |scratch, ((((x, y), z), w), w2)| {
+                            let scratch = &mut scratch[..2 * x.len()];
+                            radix3_butterflies(x, y, z, w, w2, beta, scratch);
+                        },
+                    );
+            }
+            return;
+        }
+    }
+
+    for_each_block(coeffs, 3 * distance, 2 * columns, |block, scratch| {
+        let (x, y, z) = thirds(block, distance, columns);
+        radix3_butterflies(x, y, z, twiddles, twiddles2, beta, scratch);
+    });
+}
+
+// Two radix 3 layers in one pass, combining the sub-FFTs of size distance into sub-FFTs
+// of nine times the size, on rows of width values. With d = distance / width,
+// inner = [twiddles, twiddles2] of the layer at distance, the powers w^(3k) and w^(6k)
+// of a root of unity w of order 9 * d, and outer the same for the layer at
+// 3 * distance, w^k and w^(2k) for k < 3 * d. beta is the cube root of unity w^(3 * d).
+// Each block of 9 * distance values goes through the layer at distance on its thirds
+// and then through the one at 3 * distance while it is in cache. Only the first
+// inner[0].len() rows of butterflies in each ninth are computed
+fn radix9_layer<F: BatchField>(
+    coeffs: &mut [F],
+    distance: usize,
+    width: usize,
+    inner: [&[F]; 2],
+    outer: [&[F]; 2],
+    beta: &F,
+) {
+    let columns = inner[0].len() * width;
+    let d = distance / width;
+    for_each_block(coeffs, 9 * distance, 2 * columns, |block, scratch| {
+        for third in block.chunks_mut(3 * distance) {
+            let (x, y, z) = thirds(third, distance, columns);
+            radix3_butterflies(x, y, z, inner[0], inner[1], beta, scratch);
+        }
+        let (x, yz) = block.split_at_mut(3 * distance);
+        let (y, z) = yz.split_at_mut(3 * distance);
+        for ninth in 0..3 {
+            let range = ninth * distance..ninth * distance + columns;
+            let twiddles = ninth * d..ninth * d + inner[0].len();
+            radix3_butterflies(
+                &mut x[range.clone()],
+                &mut y[range.clone()],
+                &mut z[range],
+                &outer[0][twiddles.clone()],
+                &outer[1][twiddles],
+                beta,
+                scratch,
+            );
+        }
+    });
+}
+
+// The first two radix 3 layers, on blocks of 9 rows of width values. The inner
+// butterflies have no twiddles and the outer ones w^k and w^(2k) for k < 3, for w[k]
+// the powers of a ninth root of unity, so 10 multiplications per 9 values instead of
+// 18. Single values are transposed as in radix4_first_layers
+fn radix9_first_layers<F: BatchField>(coeffs: &mut [F], width: usize, w: &[F], beta: &F) {
+    if width > 1 {
+        return for_each_block(coeffs, 9 * width, 2 * width, |block, uv| {
+            radix9_first_butterflies(block, width, w, beta, uv)
+        });
+    }
+    for_each_block(
+        coeffs,
+        9 * SMALL_BLOCKS,
+        11 * SMALL_BLOCKS,
+        |chunk, scratch| {
+            let blocks = chunk.len() / 9;
+            let (values, uv) = scratch[..11 * blocks].split_at_mut(9 * blocks);
+            transpose(chunk, values, blocks, 9);
+            radix9_first_butterflies(values, blocks, w, beta, uv);
+            transpose(values, chunk, 9, blocks);
+        },
+    );
+}
+
+// the butterflies of radix9_first_layers on values, 9 vectors of length len, using the
+// scratch uv
+fn radix9_first_butterflies<F: BatchField>(
+    values: &mut [F],
+    len: usize,
+    w: &[F],
+    beta: &F,
+    uv: &mut [F],
+) {
+    let (u, v) = uv.split_at_mut(len);
+    for third in values.chunks_mut(3 * len) {
+        let (x, y, z) = thirds(third, len, len);
+        u.copy_from_slice(y);
+        v.copy_from_slice(z);
+        dft3(x, y, z, u, v, beta);
+    }
+    for k in 1..3 {
+        F::batch_scale(&mut values[(3 + k) * len..(4 + k) * len], &w[k]);
+        F::batch_scale(&mut values[(6 + k) * len..(7 + k) * len], &w[2 * k]);
+    }
+    let (x, yz) = values.split_at_mut(3 * len);
+    let (y, z) = yz.split_at_mut(3 * len);
+    for ninth in 0..3 {
+        let range = ninth * len..(ninth + 1) * len;
+        u.copy_from_slice(&y[range.clone()]);
+        v.copy_from_slice(&z[range.clone()]);
+        dft3(
+            &mut x[range.clone()],
+            &mut y[range.clone()],
+            &mut z[range],
+            u,
+            v,
+            beta,
+        );
+    }
+}
+
+// the first columns of the thirds of a block
+fn thirds<F>(block: &mut [F], distance: usize, columns: usize) -> (&mut [F], &mut [F], &mut [F]) {
+    let (x, yz) = block.split_at_mut(distance);
+    let (y, z) = yz.split_at_mut(distance);
+    return (&mut x[..columns], &mut y[..columns], &mut z[..columns]);
+}
+
+// butterflies on the thirds (x, y, z) of a block, for u = w*y and v = w^2*z in the
+// first and second half of scratch
+fn radix3_butterflies<F: BatchField>(
+    x: &mut [F],
+    y: &mut [F],
+    z: &mut [F],
+    twiddles: &[F],
+    twiddles2: &[F],
+    beta: &F,
+    scratch: &mut [F],
+) {
+    let (u, v) = scratch.split_at_mut(x.len());
+    u.copy_from_slice(y);
+    v.copy_from_slice(z);
+    mul_twiddles(u, twiddles);
+    mul_twiddles(v, twiddles2);
+    dft3(x, y, z, u, v, beta);
+}
+
+// values times the twiddles, each on a row of values.len() / twiddles.len() values
+fn mul_twiddles<F: BatchField>(values: &mut [F], twiddles: &[F]) {
+    if values.len() == twiddles.len() {
+        return F::batch_mul(values, twiddles);
+    }
+    let width = values.len() / twiddles.len();
+    for (row, w) in values.chunks_mut(width).zip(twiddles) {
+        F::batch_scale(row, w);
+    }
+}
+
+// (x, y, z) to x + u + v, x + beta*u + beta^2*v, x + beta^2*u + beta*v, where u and v
+// are y and z times their twiddles. As beta^2 = -1 - beta these are x + u + v,
+// x - v + m, x - u - m for m = beta*(u - v), taking a single scaling by beta. Overwrites
+// u
+fn dft3<F: BatchField>(x: &mut [F], y: &mut [F], z: &mut [F], u: &mut [F], v: &[F], beta: &F) {
+    y.copy_from_slice(x);
+    z.copy_from_slice(x);
+    F::batch_add(x, u);
+    F::batch_add(x, v);
+    F::batch_sub(y, v);
+    F::batch_sub(z, u);
+
+    F::batch_sub(u, v);
+    F::batch_scale(u, beta);
+    F::batch_add(y, u);
+    F::batch_sub(z, u);
+}
+
+// sets twiddles to 1, w, ..., w^(n-1), doubling the powers computed so far each round
+fn twiddle_factors<F: BatchField>(twiddles: &mut Vec<F>, w: &F, n: usize) {
+    twiddles.clear();
+    twiddles.push(F::one());
+    let mut w_len = *w; // w^twiddles.len()
+    while twiddles.len() < n {
+        let len = twiddles.len();
+        let extend = std::cmp::min(len, n - len);
+        twiddles.extend_from_within(..extend);
+        F::batch_scale(&mut twiddles[len..], &w_len);
+        w_len.square();
+    }
+}
+
+pub fn fft3_inverse<F: BatchField>(points: &mut [F], beta: &F) {
+    let len_inv = from_usize::<F>(points.len()).inverse().unwrap();
     let beta_inv = beta.inverse().unwrap();
     fft3_in_place::<F>(points, &beta_inv);
-    points
-        .iter_mut()
-        .for_each(|coeff| coeff.mul_assign(&len_inv));
+    F::batch_scale(points, &len_inv);
 }
 
-// TODO: dont use from_str hack
-pub fn fft2_inverse<F: Field + PrimeField>(points: &mut [F], alpha: &F) {
-    let len_inv = F::from_str(&points.len().to_string())
-        .unwrap()
-        .inverse()
-        .unwrap();
+pub fn fft2_inverse<F: BatchField>(points: &mut [F], alpha: &F) {
+    let len_inv = from_usize::<F>(points.len()).inverse().unwrap();
     let alpha_inv = alpha.inverse().unwrap();
     fft2_in_place::<F>(points, &alpha_inv);
-    points
-        .iter_mut()
-        .for_each(|coeff| coeff.mul_assign(&len_inv));
+    F::batch_scale(points, &len_inv);
+}
+
+// n as a field element, by double-and-add
+fn from_usize<F: Field>(n: usize) -> F {
+    let mut result = F::zero();
+    for i in (0..usize::BITS - n.leading_zeros()).rev() {
+        result.double();
+        if (n >> i) & 1 == 1 {
+            result.add_assign(&F::one());
+        }
+    }
+    return result;
 }
 
 // does digit-reversal permutation of the data in the given base
@@ -173,14 +1004,10 @@ pub fn digit_reverse_swap<T: Sized + Copy>(data: &mut [T], base: usize) {
     let seed = seed_table(base, n, n1);
     for i in 0..(n1 - 1) {
         for j in (i + 1)..n1 {
-            let temp = data[i + seed[j]];
-            data[i + seed[j]] = data[seed[i] + j];
-            data[seed[i] + j] = temp;
+            data.swap(i + seed[j], seed[i] + j);
             if p_odd {
                 for z in 1..base {
-                    let temp = data[i + seed[j] + (z * n1)];
-                    data[i + seed[j] + (z * n1)] = data[seed[i] + j + (z * n1)];
-                    data[seed[i] + j + (z * n1)] = temp;
+                    data.swap(i + seed[j] + (z * n1), seed[i] + j + (z * n1));
                 }
             }
         }
@@ -192,13 +1019,13 @@ fn calc_n1(base: usize, n: usize) -> (usize, bool) {
     let mut t = 1usize;
     while t < n {
         k += 1;
-        t = t * base;
+        t *= base;
     }
     if t != n {
-        panic!(format!(
+        panic!(
             "Cant do digit-reversal reordering. Data size {} is not on the form {}^{}",
             n, base, k
-        ));
+        );
     }
 
     if k.trailing_zeros() == 0 {
@@ -226,129 +1053,160 @@ fn seed_table(base: usize, n: usize, n1: usize) -> Vec<usize> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::field::{Fp, OleField};
+    use crate::extension::tests::Fq2;
+    use crate::field::{FiniteField, Fp, OleField};
+    use crate::field64::Fp64;
     use crate::poly::horner;
+    use ff::PrimeField;
     use rand;
 
     #[test]
     fn test_fft2() {
+        check_fft2::<Fp>();
+        check_fft2::<Fp64>();
+        check_fft2::<Fq2>();
+    }
+
+    fn check_fft2<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let coeffs: Vec<Fp> = (0..Fp::A).map(|_| Fp::random(&mut rng)).collect();
-        let points = fft2(&coeffs, &Fp::alpha());
-        for i in 0..Fp::A {
-            assert_eq!(points[i], horner(&coeffs, &Fp::alpha().pow(&[i as u64])));
+        let coeffs: Vec<F> = (0..F::A).map(|_| F::random(&mut rng)).collect();
+        let points = fft2(&coeffs, &F::alpha());
+        for (i, point) in points.iter().enumerate() {
+            assert_eq!(*point, horner(&coeffs, &F::alpha().pow([i as u64])));
         }
     }
 
     #[test]
     fn test_fft2_in_place() {
+        check_fft2_in_place::<Fp>();
+        check_fft2_in_place::<Fp64>();
+    }
+
+    fn check_fft2_in_place<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let coeffs: Vec<Fp> = (0..Fp::A).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..F::A).map(|_| F::random(&mut rng)).collect();
         let mut points = coeffs.to_vec();
-        fft2_in_place(&mut points, &Fp::alpha());
-        for i in 0..Fp::A {
-            let actual = points[i];
-            let expected = horner(&coeffs, &Fp::alpha().pow(&[i as u64]));
+        fft2_in_place(&mut points, &F::alpha());
+        for (i, actual) in points.iter().enumerate() {
+            let expected = horner(&coeffs, &F::alpha().pow([i as u64]));
             assert!(
-                actual == expected,
-                format!(
-                    "point {} is incorrect\n\
+                *actual == expected,
+                "point {} is incorrect\n\
                              \tfound:    {:?}\n\
                              \texpected: {:?}",
-                    i, actual, expected
-                )
+                i,
+                actual,
+                expected
             );
         }
 
-        let coeffs: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
         let mut points = coeffs.to_vec();
-        fft2_in_place(&mut points, &Fp::alpha().pow([2u64]));
-        for i in 0..Fp::A / 2 {
-            let actual = points[i];
-            let expected = horner(&coeffs, &Fp::alpha().pow(&[2 * i as u64]));
+        fft2_in_place(&mut points, &F::alpha().pow([2u64]));
+        for (i, actual) in points.iter().enumerate() {
+            let expected = horner(&coeffs, &F::alpha().pow([2 * i as u64]));
             assert!(
-                actual == expected,
-                format!(
-                    "point {} is incorrect\n\
+                *actual == expected,
+                "point {} is incorrect\n\
                              \tfound:    {:?}\n\
                              \texpected: {:?}",
-                    i, actual, expected
-                )
+                i,
+                actual,
+                expected
             );
         }
     }
 
     #[test]
     fn test_fft2_inverse() {
+        check_fft2_inverse::<Fp>();
+        check_fft2_inverse::<Fp64>();
+    }
+
+    fn check_fft2_inverse<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let points: Vec<Fp> = (0..Fp::A).map(|_| Fp::random(&mut rng)).collect();
+        let points: Vec<F> = (0..F::A).map(|_| F::random(&mut rng)).collect();
         let mut coeffs = points.to_vec();
-        fft2_inverse(&mut coeffs, &Fp::alpha());
+        fft2_inverse(&mut coeffs, &F::alpha());
         points.iter().enumerate().for_each(|(idx, point)| {
-            assert_eq!(*point, horner(&coeffs, &Fp::alpha().pow(&[idx as u64])));
+            assert_eq!(*point, horner(&coeffs, &F::alpha().pow([idx as u64])));
         })
     }
 
     #[test]
     fn test_fft3() {
+        check_fft3::<Fp>();
+        check_fft3::<Fp64>();
+        check_fft3::<Fq2>();
+    }
+
+    fn check_fft3<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let coeffs: Vec<Fp> = (0..(Fp::B)).map(|_| Fp::random(&mut rng)).collect();
-        let points = fft3(&coeffs, &Fp::beta());
-        for i in 0..100 {
-            assert_eq!(points[i], horner(&coeffs, &Fp::beta().pow(&[i as u64])));
+        let coeffs: Vec<F> = (0..(F::B)).map(|_| F::random(&mut rng)).collect();
+        let points = fft3(&coeffs, &F::beta());
+        for (i, point) in points.iter().take(100).enumerate() {
+            assert_eq!(*point, horner(&coeffs, &F::beta().pow([i as u64])));
         }
     }
 
     #[test]
     fn test_fft3_in_place() {
+        check_fft3_in_place::<Fp>();
+        check_fft3_in_place::<Fp64>();
+    }
+
+    fn check_fft3_in_place<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let coeffs: Vec<Fp> = (0..(Fp::B)).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..(F::B)).map(|_| F::random(&mut rng)).collect();
         let mut points = coeffs.to_vec();
-        fft3_in_place(&mut points, &Fp::beta());
-        for i in 0..102 {
-            let actual = points[i];
-            let expected = horner(&coeffs, &Fp::beta().pow(&[i as u64]));
+        fft3_in_place(&mut points, &F::beta());
+        for (i, actual) in points.iter().take(102).enumerate() {
+            let expected = horner(&coeffs, &F::beta().pow([i as u64]));
             assert!(
-                actual == expected,
-                format!(
-                    "point {} is incorrect\n\
+                *actual == expected,
+                "point {} is incorrect\n\
                              \tfound:    {:?}\n\
                              \texpected: {:?}",
-                    i, actual, expected
-                )
+                i,
+                actual,
+                expected
             );
         }
 
-        let coeffs: Vec<Fp> = (0..(Fp::B / 9)).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..(F::B / 9)).map(|_| F::random(&mut rng)).collect();
         let mut points = coeffs.to_vec();
-        fft3_in_place(&mut points, &Fp::beta().pow([9]));
-        for i in 0..102 {
-            let actual = points[i];
-            let expected = horner(&coeffs, &Fp::beta().pow(&[9 * i as u64]));
+        fft3_in_place(&mut points, &F::beta().pow([9]));
+        for (i, actual) in points.iter().take(102).enumerate() {
+            let expected = horner(&coeffs, &F::beta().pow([9 * i as u64]));
             assert!(
-                actual == expected,
-                format!(
-                    "point {} is incorrect\n\
+                *actual == expected,
+                "point {} is incorrect\n\
                              \tfound:    {:?}\n\
                              \texpected: {:?}",
-                    i, actual, expected
-                )
+                i,
+                actual,
+                expected
             );
         }
     }
 
     #[test]
     fn test_fft3_inverse() {
+        check_fft3_inverse::<Fp>();
+        check_fft3_inverse::<Fp64>();
+    }
+
+    fn check_fft3_inverse<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let points: Vec<Fp> = (0..(Fp::B)).map(|_| Fp::random(&mut rng)).collect();
+        let points: Vec<F> = (0..(F::B)).map(|_| F::random(&mut rng)).collect();
         let mut coeffs = points.to_vec();
-        fft3_inverse(&mut coeffs, &Fp::beta());
+        fft3_inverse(&mut coeffs, &F::beta());
         points
             .iter()
             .take(100)
             .enumerate()
             .for_each(|(idx, point)| {
-                assert_eq!(*point, horner(&coeffs, &Fp::beta().pow(&[idx as u64])));
+                assert_eq!(*point, horner(&coeffs, &F::beta().pow([idx as u64])));
             })
     }
 
@@ -385,21 +1243,323 @@ mod tests {
         assert_eq!(table[8], 72);
     }
 
+    #[test]
+    fn test_fft_mixed() {
+        check_fft_mixed::<Fp>();
+        check_fft_mixed::<Fp64>();
+        check_fft_mixed::<Fq2>();
+    }
+
+    fn check_fft_mixed<F: OleField>() {
+        let mut rng = rand::thread_rng();
+
+        // agrees with the reference implementations on powers of 2 and 3
+        let coeffs: Vec<F> = (0..F::A).map(|_| F::random(&mut rng)).collect();
+        let mut values = coeffs.to_vec();
+        EvaluationDomain::new(F::A, F::alpha()).fft(&mut values);
+        assert_eq!(values, fft2(&coeffs, &F::alpha()));
+
+        let coeffs: Vec<F> = (0..F::B).map(|_| F::random(&mut rng)).collect();
+        let mut values = coeffs.to_vec();
+        EvaluationDomain::new(F::B, F::beta()).fft(&mut values);
+        assert_eq!(values, fft3(&coeffs, &F::beta()));
+
+        for (a, b) in [(1, 1), (2, 3), (4, 1), (3, 4), (5, 3), (0, 4), (6, 0)].iter() {
+            let n = 2usize.pow(*a) * 3usize.pow(*b);
+            let omega = F::subgroup_generator(n as u64).unwrap();
+            let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+            let domain = EvaluationDomain::new(n, omega);
+            let mut values = coeffs.to_vec();
+            domain.fft(&mut values);
+            for (i, y) in values.iter().enumerate() {
+                assert_eq!(*y, horner(&coeffs, &omega.pow([i as u64])));
+            }
+
+            domain.fft_inverse(&mut values);
+            assert_eq!(values, coeffs);
+        }
+    }
+
+    #[test]
+    fn test_evaluation_domain() {
+        check_evaluation_domain::<Fp>();
+        check_evaluation_domain::<Fp64>();
+        check_evaluation_domain::<Fq2>();
+    }
+
+    fn check_evaluation_domain<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 2, 3, 4, 9, 12, 64, 216, 729].iter() {
+            let domain = EvaluationDomain::new(*n, F::subgroup_generator(*n as u64).unwrap());
+            let elements: Vec<F> = domain.elements().collect();
+            assert_eq!(elements.len(), *n);
+            for (i, x) in elements.iter().enumerate() {
+                assert_eq!(*x, domain.generator().pow([i as u64]));
+                assert_eq!(*x, domain.element(i + *n));
+            }
+
+            let coeffs: Vec<F> = (0..*n).map(|_| F::random(&mut rng)).collect();
+            let mut values = coeffs.to_vec();
+            domain.fft(&mut values);
+            for (x, y) in elements.iter().zip(&values) {
+                assert_eq!(*y, horner(&coeffs, x));
+            }
+            domain.fft_inverse(&mut values);
+            assert_eq!(values, coeffs);
+
+            let shift = F::random(&mut rng);
+            domain.coset_fft(&mut values, &shift);
+            for (x, y) in elements.iter().zip(&values) {
+                let mut z = *x;
+                z.mul_assign(&shift);
+                assert_eq!(*y, horner(&coeffs, &z));
+            }
+            domain.coset_fft_inverse(&mut values, &shift);
+            assert_eq!(values, coeffs);
+        }
+
+        // large enough for the parallel butterflies
+        let n = F::A * 27;
+        let domain = EvaluationDomain::new(n, F::subgroup_generator(n as u64).unwrap());
+        let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let mut values = coeffs.to_vec();
+        domain.fft(&mut values);
+        for i in (0..n).step_by(n / 8 + 1) {
+            assert_eq!(values[i], horner(&coeffs, &domain.element(i)));
+        }
+        domain.fft_inverse(&mut values);
+        assert_eq!(values, coeffs);
+
+        // agrees with the free functions on the alpha and beta subgroups
+        let coeffs: Vec<F> = (0..F::B).map(|_| F::random(&mut rng)).collect();
+        let mut values = coeffs.to_vec();
+        EvaluationDomain::new(F::B, F::beta()).fft(&mut values);
+        let mut expected = coeffs.to_vec();
+        fft3_in_place(&mut expected, &F::beta());
+        assert_eq!(values, expected);
+
+        let mut values = coeffs[..F::A].to_vec();
+        EvaluationDomain::new(F::A, F::alpha()).fft_inverse(&mut values);
+        let mut expected = coeffs[..F::A].to_vec();
+        fft2_inverse(&mut expected, &F::alpha());
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn test_fft_pruned() {
+        check_fft_pruned::<Fp>();
+        check_fft_pruned::<Fp64>();
+        check_fft_pruned::<Fq2>();
+    }
+
+    fn check_fft_pruned<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 6, 64, 216, F::B, F::A * 27].iter() {
+            let domain = EvaluationDomain::new(*n, F::subgroup_generator(*n as u64).unwrap());
+            for nonzero in [0, 1, 2, 3, *n / 9, *n / 4 + 1, *n]
+                .iter()
+                .filter(|m| *m <= n)
+            {
+                let coeffs: Vec<F> = (0..*nonzero).map(|_| F::random(&mut rng)).collect();
+                let mut expected = coeffs.to_vec();
+                expected.resize_with(*n, F::zero);
+                domain.fft(&mut expected);
+                for outputs in [0, 1, *n / 3, *n / 2 + 1, *n].iter() {
+                    let values = domain.fft_pruned(&coeffs, *outputs);
+                    assert_eq!(values, &expected[..*outputs]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_six_step_fft() {
+        check_six_step_fft::<Fp>();
+        check_six_step_fft::<Fp64>();
+        check_six_step_fft::<Fq2>();
+
+        // the in place FFTs switch to six-step at the sizes of bench_fft_large, and agree
+        // with their butterfly layers
+        let mut rng = rand::thread_rng();
+        for (n, radix) in [(1 << 20, 2), (3usize.pow(13), 3)].iter() {
+            let omega = FpLarge::subgroup_generator(*n as u64).unwrap();
+            let coeffs: Vec<FpLarge> = (0..*n).map(|_| FpLarge::random(&mut rng)).collect();
+            let mut values = coeffs.to_vec();
+            if *radix == 2 {
+                fft2_in_place(&mut values, &omega);
+            } else {
+                fft3_in_place(&mut values, &omega);
+            }
+            let mut expected = coeffs.to_vec();
+            fft_layers(&mut expected, &omega, *radix);
+            assert_eq!(values, expected);
+        }
+    }
+
+    // q = 2^20 * 3^13 * 541 * 5099 + 1, for the large FFTs
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "4611672963082616833"]
+    #[PrimeFieldGenerator = "5"]
+    struct FpLarge(FpLargeRepr);
+
+    impl BatchField for FpLarge {}
+
+    // compares with the butterfly layers of fft_pruned, and evaluation at some points
+    fn check_six_step_fft<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let n = F::A * 729;
+        assert!(n >= SIX_STEP_MIN_LEN);
+        let domain = EvaluationDomain::new(n, F::subgroup_generator(n as u64).unwrap());
+        let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let mut values = coeffs.to_vec();
+        domain.fft(&mut values);
+        assert_eq!(values, domain.fft_pruned(&coeffs, n));
+        for i in [0, 1, 2, n / 3 + 1, n - 1].iter() {
+            assert_eq!(values[*i], horner(&coeffs, &domain.element(*i)));
+        }
+
+        domain.fft_inverse(&mut values);
+        assert_eq!(values, coeffs);
+    }
+
+    #[test]
+    fn test_higher_radix_layers() {
+        check_higher_radix_layers::<Fp>();
+        check_higher_radix_layers::<Fp64>();
+        check_higher_radix_layers::<Fq2>();
+    }
+
+    // even and odd exponents, so both the radix 4 and 9 kernels and a left over radix 2
+    // or 3 layer, against the reference FFTs and evaluation
+    fn check_higher_radix_layers<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        for k in 2..=8 {
+            let n = 1 << k;
+            let alpha = F::alpha().pow([(F::A / n) as u64]);
+            let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+            let mut values = coeffs.to_vec();
+            fft2_in_place(&mut values, &alpha);
+            assert_eq!(values, fft2(&coeffs, &alpha));
+        }
+        for k in 2..=6 {
+            let n = 3usize.pow(k);
+            let beta = F::beta().pow([(F::B / n) as u64]);
+            let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+            let mut values = coeffs.to_vec();
+            fft3_in_place(&mut values, &beta);
+            assert_eq!(values, fft3(&coeffs, &beta));
+        }
+
+        for (a, b) in [(1, 1), (2, 2), (3, 1), (1, 3), (4, 3), (5, 4)].iter() {
+            let n = 2usize.pow(*a) * 3usize.pow(*b);
+            let domain = EvaluationDomain::new(n, F::subgroup_generator(n as u64).unwrap());
+            let coeffs: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+            let expected: Vec<F> = domain.elements().map(|x| horner(&coeffs, &x)).collect();
+            let mut values = coeffs.to_vec();
+            domain.fft(&mut values);
+            assert_eq!(values, expected);
+
+            // prunes columns within the radix 4 and 9 blocks
+            let short: Vec<F> = coeffs[..n / 5 + 1].to_vec();
+            for outputs in [1, 5, n / 7, n / 2 + 1].iter() {
+                let values = domain.fft_pruned(&short, *outputs);
+                for (i, x) in values.iter().enumerate() {
+                    assert_eq!(*x, horner(&short, &domain.element(i)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fft_batch() {
+        check_fft_batch::<Fp>();
+        check_fft_batch::<Fp64>();
+        check_fft_batch::<Fq2>();
+
+        // large vectors go through the six-step FFT one by one
+        let mut rng = rand::thread_rng();
+        let n = SIX_STEP_MIN_LEN;
+        let alpha = Fp64::subgroup_generator(n as u64).unwrap();
+        let vectors: Vec<Vec<Fp64>> = (0..BATCH_MIN_WIDTH)
+            .map(|_| (0..n).map(|_| Fp64::random(&mut rng)).collect())
+            .collect();
+        let mut batch = vectors.clone();
+        fft2_batch(&mut batch, &alpha);
+        let domain = EvaluationDomain::new(n, alpha);
+        for (v, x) in vectors.iter().zip(&batch) {
+            assert_eq!(*x, domain.fft_pruned(v, n));
+        }
+    }
+
+    // against the FFTs of the vectors one by one, for numbers of vectors below and above
+    // BATCH_MIN_WIDTH
+    fn check_fft_batch<F: OleField>() {
+        let random_vectors = |count: usize, n: usize| -> Vec<Vec<F>> {
+            let mut rng = rand::thread_rng();
+            (0..count)
+                .map(|_| (0..n).map(|_| F::random(&mut rng)).collect())
+                .collect()
+        };
+        for count in [0, 1, 5, 8].iter() {
+            let vectors = random_vectors(*count, F::A);
+            let mut batch = vectors.clone();
+            fft2_batch(&mut batch, &F::alpha());
+            for (v, x) in vectors.iter().zip(&batch) {
+                let mut expected = v.clone();
+                fft2_in_place(&mut expected, &F::alpha());
+                assert_eq!(*x, expected);
+            }
+            fft2_batch_inverse(&mut batch, &F::alpha());
+            assert_eq!(batch, vectors);
+
+            let vectors = random_vectors(*count, F::B);
+            let mut batch = vectors.clone();
+            fft3_batch(&mut batch, &F::beta());
+            for (v, x) in vectors.iter().zip(&batch) {
+                let mut expected = v.clone();
+                fft3_in_place(&mut expected, &F::beta());
+                assert_eq!(*x, expected);
+            }
+            fft3_batch_inverse(&mut batch, &F::beta());
+            assert_eq!(batch, vectors);
+
+            for n in [12, 216].iter() {
+                let domain = EvaluationDomain::new(*n, F::subgroup_generator(*n as u64).unwrap());
+                let vectors = random_vectors(*count, *n);
+                let mut batch = vectors.clone();
+                domain.fft_batch(&mut batch);
+                for (v, x) in vectors.iter().zip(&batch) {
+                    for i in [0, 1, n / 2, n - 1].iter() {
+                        assert_eq!(x[*i], horner(v, &domain.element(*i)));
+                    }
+                }
+                domain.fft_batch_inverse(&mut batch);
+                assert_eq!(batch, vectors);
+            }
+        }
+    }
+
     #[test]
     fn test_fft_fft_inverse_identity() {
+        check_fft_fft_inverse_identity::<Fp>();
+        check_fft_fft_inverse_identity::<Fp64>();
+        check_fft_fft_inverse_identity::<Fq2>();
+    }
+
+    fn check_fft_fft_inverse_identity<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let coeffs: Vec<Fp> = (0..(Fp::B)).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..(F::B)).map(|_| F::random(&mut rng)).collect();
         let mut coeffs_clone = coeffs.to_vec();
-        fft3_in_place(&mut coeffs_clone, &Fp::beta());
-        fft3_inverse(&mut coeffs_clone, &Fp::beta());
+        fft3_in_place(&mut coeffs_clone, &F::beta());
+        fft3_inverse(&mut coeffs_clone, &F::beta());
         for (c_actual, c_expected) in coeffs_clone.iter().zip(&coeffs) {
             assert_eq!(*c_actual, *c_expected);
         }
 
-        let coeffs: Vec<Fp> = (0..(Fp::A)).map(|_| Fp::random(&mut rng)).collect();
+        let coeffs: Vec<F> = (0..(F::A)).map(|_| F::random(&mut rng)).collect();
         let mut coeffs_clone = coeffs.to_vec();
-        fft2_in_place(&mut coeffs_clone, &Fp::alpha());
-        fft2_inverse(&mut coeffs_clone, &Fp::alpha());
+        fft2_in_place(&mut coeffs_clone, &F::alpha());
+        fft2_inverse(&mut coeffs_clone, &F::alpha());
         for (c_actual, c_expected) in coeffs_clone.iter().zip(&coeffs) {
             assert_eq!(*c_actual, *c_expected);
         }
@@ -1,88 +1,369 @@
 use crate::fft;
 use ff::{Field, PrimeField, PrimeFieldDecodingError, PrimeFieldRepr};
-use scuttlebutt::Block;
+pub use ole_derive::OleField;
 
 // Prime q = 152137607412117916810699707336809121793, with bit size 127
 // q = (138 * 54697345034152330060240659727 * 20155392) + 1
 // factors of q-1: 2^11 * 3^10 * 23 * 54697345034152330060240659727
 // generator of multiplicative group: 5
-#[derive(PrimeField)]
+#[derive(PrimeField, OleField)]
 #[PrimeFieldModulus = "152137607412117916810699707336809121793"]
 #[PrimeFieldGenerator = "5"]
+#[OleFieldAlphaOrder = "256"]
+#[OleFieldBetaOrder = "2187"]
+#[OleFieldCrate = "crate"]
 pub struct Fp(FpRepr);
 
+// The operations of a finite field the protocols need besides those of ff::Field.
+// Implemented for every prime field, and for the extension fields of extension.rs
+pub trait FiniteField: Field {
+    // number of bytes of the canonical encoding
+    const BYTES: usize;
+
+    // the field has at least 2^SIZE_BITS elements
+    const SIZE_BITS: u32;
+
+    fn encode(self, bytes: &mut [u8]);
+
+    // Strict decoding of encode, rejecting bytes that don't encode an element
+    fn decode(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError>;
+
+    // g^((|F|-1)/n) for the multiplicative generator g, if n divides |F|-1
+    fn subgroup_generator(n: u64) -> Option<Self>;
+
+    // Rejection sampling from Self::BYTES uniformly random bytes: clears the bits above
+    // the bit size of the field and decodes, None if the result encodes no element
+    fn from_random_bytes(bytes: &mut [u8]) -> Option<Self>;
+}
+
+impl<F: PrimeField> FiniteField for F {
+    const BYTES: usize = (F::NUM_BITS as usize).div_ceil(8);
+
+    // q >= 2^(NUM_BITS - 1)
+    const SIZE_BITS: u32 = F::NUM_BITS - 1;
+
+    // Canonical encoding: the integer representative in [0, q) as Self::BYTES
+    // little-endian bytes
+    fn encode(self, bytes: &mut [u8]) {
+        assert_eq!(bytes.len(), Self::BYTES);
+        let repr = self.into_repr();
+        for (chunk, limb) in bytes.chunks_mut(8).zip(repr.as_ref()) {
+            let n = chunk.len();
+            chunk.copy_from_slice(&limb.to_le_bytes()[..n]);
+        }
+    }
+
+    // rejects bytes that encode an integer >= q
+    fn decode(bytes: &[u8]) -> Result<Self, PrimeFieldDecodingError> {
+        assert_eq!(bytes.len(), Self::BYTES);
+        let mut repr = F::Repr::default();
+        for (limb, chunk) in repr.as_mut().iter_mut().zip(bytes.chunks(8)) {
+            let mut limb_bytes = [0u8; 8];
+            limb_bytes[..chunk.len()].copy_from_slice(chunk);
+            *limb = u64::from_le_bytes(limb_bytes);
+        }
+        F::from_repr(repr)
+    }
+
+    fn subgroup_generator(n: u64) -> Option<Self> {
+        let mut p_minus_one = F::char();
+        p_minus_one.sub_noborrow(&1.into());
+        let exp = div_exact(p_minus_one.as_ref(), n)?;
+        return Some(F::multiplicative_generator().pow(&exp));
+    }
+
+    fn from_random_bytes(bytes: &mut [u8]) -> Option<Self> {
+        let excess = Self::BYTES * 8 - F::NUM_BITS as usize;
+        bytes[Self::BYTES - 1] &= 0xff >> excess;
+        Self::decode(bytes).ok()
+    }
+}
+
+// limbs/n for little-endian u64 limbs, if n divides the integer
+pub(crate) fn div_exact(limbs: &[u64], n: u64) -> Option<Vec<u64>> {
+    let mut quotient = vec![0u64; limbs.len()];
+    let mut rem = 0u128;
+    for i in (0..limbs.len()).rev() {
+        let cur = (rem << 64) | limbs[i] as u128;
+        quotient[i] = (cur / n as u128) as u64;
+        rem = cur % n as u128;
+    }
+    if rem != 0 {
+        return None;
+    }
+    return Some(quotient);
+}
+
 // A field with special properties that enable interpolation and evaluation at a set of
 // predetermined alphas and betas in the field using FFT.
-pub trait OleField: PrimeField + Field + From<Block> {
+pub trait OleField: FiniteField + BatchField {
     fn alpha() -> Self; // generator of order A multiplicative subgroup
     const A: usize; // order of alpha()
 
     fn beta() -> Self; // generator of order B multiplicative subgroup
     const B: usize; // order of beta()
 
+    // Checks what the type can't guarantee about the field, like the irreducibility of
+    // an extension field's modulus. Asserted once by OleParams on construction
+    fn is_valid_field() -> bool {
+        true
+    }
+
     // In-place radix2 DIT FFT
     // After inputting coeffs with coeffs.len() = n = 2^k we have coeffs[i] = f(alpha^i)
     // where f(x) = coeffs[0] + coeffs[1]*x + ... + coeffs[n-1]*x^(n-1)
     // Precondition: alpha^n = 1
-    fn fft2(coeffs: &mut [Self], alpha: &Self);
+    fn fft2(coeffs: &mut [Self], alpha: &Self) {
+        fft::fft2_in_place(coeffs, alpha);
+    }
 
     // In-place radix3 DIT FFT
     // After inputting coeffs with coeffs.len() = n = 3^k we have coeffs[i] = f(beta^i)
     // where f(x) = coeffs[0] + coeffs[1]*x + ... + coeffs[n-1]*x^(n-1)
     // Precondition: beta^n = 1
-    fn fft3(coeffs: &mut [Self], beta: &Self);
+    fn fft3(coeffs: &mut [Self], beta: &Self) {
+        fft::fft3_in_place(coeffs, beta);
+    }
 
     // Inverse radix2 DIT FFT
     // Inverse of fft2
-    fn fft2_inverse(ys: &mut [Self], alpha: &Self);
+    fn fft2_inverse(ys: &mut [Self], alpha: &Self) {
+        fft::fft2_inverse(ys, alpha);
+    }
 
     // Inverse radix3 DIT FFT
     // Inverse of fft3
-    fn fft3_inverse(ys: &mut [Self], beta: &Self);
+    fn fft3_inverse(ys: &mut [Self], beta: &Self) {
+        fft::fft3_inverse(ys, beta);
+    }
+}
 
-    fn to_block(self) -> Block;
+// Slice-wise arithmetic for the hot loops of the FFTs and the protocols. The defaults
+// do one operation per element, fields with a faster batch path override them.
+pub trait BatchField: Field {
+    // a[i] += b[i]
+    fn batch_add(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter_mut().zip(b) {
+            x.add_assign(y);
+        }
+    }
+
+    // a[i] -= b[i]
+    fn batch_sub(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter_mut().zip(b) {
+            x.sub_assign(y);
+        }
+    }
+
+    // a[i] *= b[i]
+    fn batch_mul(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter_mut().zip(b) {
+            x.mul_assign(y);
+        }
+    }
+
+    // a[i] *= c
+    fn batch_scale(a: &mut [Self], c: &Self) {
+        for x in a.iter_mut() {
+            x.mul_assign(c);
+        }
+    }
+
+    // a[i] = a[i]^-1 by Montgomery's trick, one inversion and 3(n-1) multiplications.
+    // Returns None and leaves a unchanged if some a[i] is zero.
+    fn batch_inverse(a: &mut [Self]) -> Option<()> {
+        let mut prefix = Vec::with_capacity(a.len());
+        let mut acc = Self::one();
+        for x in a.iter() {
+            prefix.push(acc);
+            acc.mul_assign(x);
+        }
+        let mut inv = acc.inverse()?;
+        for (x, p) in a.iter_mut().zip(prefix).rev() {
+            let mut x_inv = inv;
+            x_inv.mul_assign(&p);
+            inv.mul_assign(x);
+            *x = x_inv;
+        }
+        return Some(());
+    }
 }
 
-impl OleField for Fp {
-    const A: usize = 256usize; //2usize.pow(8);
-    const B: usize = 2187; //3usize.pow(7);
+// q as little-endian limbs, and -q^-1 mod 2^64
+const FP_MODULUS: [u64; 2] = [0x350c0b7585260801, 0x7274a32b3873bdef];
+const FP_INV: u64 = 0x2e4b5e0124e607ff;
+const FP_MODULUS_U128: u128 = (FP_MODULUS[1] as u128) << 64 | FP_MODULUS[0] as u128;
 
-    fn alpha() -> Self {
-        Fp(FpRepr([0xc06ef38a81bc942a, 0x0a90ee143aa2da39])).pow([4])
+// Works directly on the Montgomery form x*R mod q of the PrimeField derive, R = 2^128.
+// As 2q < 2^128, sums of two reduced elements fit in a u128 and additions are a single
+// conditional subtraction.
+impl BatchField for Fp {
+    fn batch_add(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter_mut().zip(b) {
+            *x = fp_from_u128(fp_reduce(fp_to_u128(x) + fp_to_u128(y)));
+        }
     }
 
-    fn beta() -> Self {
-        Fp(FpRepr([0x7eaba55f322cb079, 0x5bdbc004d5e45ace])).pow([9])
+    fn batch_sub(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter_mut().zip(b) {
+            let (diff, borrow) = fp_to_u128(x).overflowing_sub(fp_to_u128(y));
+            *x = fp_from_u128(if borrow {
+                diff.wrapping_add(FP_MODULUS_U128)
+            } else {
+                diff
+            });
+        }
     }
 
-    fn fft2(coeffs: &mut [Self], alpha: &Self) {
-        fft::fft2_in_place(coeffs, alpha);
+    fn batch_mul(a: &mut [Self], b: &[Self]) {
+        assert_eq!(a.len(), b.len());
+        #[cfg(target_arch = "x86_64")]
+        {
+            if has_adx() {
+                return unsafe { fp_batch_mul_adx(a, b) };
+            }
+        }
+        fp_batch_mul(a, b);
     }
 
-    fn fft3(coeffs: &mut [Self], beta: &Self) {
-        fft::fft3_in_place(coeffs, beta);
+    fn batch_scale(a: &mut [Self], c: &Self) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if has_adx() {
+                return unsafe { fp_batch_scale_adx(a, c) };
+            }
+        }
+        fp_batch_scale(a, c);
     }
 
-    fn fft2_inverse(ys: &mut [Self], alpha: &Self) {
-        fft::fft2_inverse(ys, alpha);
+    fn batch_inverse(a: &mut [Self]) -> Option<()> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if has_adx() {
+                return unsafe { fp_batch_inverse_adx(a) };
+            }
+        }
+        return fp_batch_inverse(a);
     }
+}
 
-    fn fft3_inverse(ys: &mut [Self], beta: &Self) {
-        fft::fft3_inverse(ys, beta);
+// On x86_64 the multiplication loops are also compiled for BMI2 and ADX and picked at
+// runtime when the CPU has them, mulx/adcx/adox make the 64x64 -> 128 bit products of
+// fp_mul about a quarter faster than the baseline x86_64 code.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn has_adx() -> bool {
+    return is_x86_feature_detected!("bmi2") && is_x86_feature_detected!("adx");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2,adx")]
+unsafe fn fp_batch_mul_adx(a: &mut [Fp], b: &[Fp]) {
+    fp_batch_mul(a, b);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2,adx")]
+unsafe fn fp_batch_scale_adx(a: &mut [Fp], c: &Fp) {
+    fp_batch_scale(a, c);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2,adx")]
+unsafe fn fp_batch_inverse_adx(a: &mut [Fp]) -> Option<()> {
+    return fp_batch_inverse(a);
+}
+
+#[inline(always)]
+fn fp_batch_mul(a: &mut [Fp], b: &[Fp]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x = fp_mul(x, y);
     }
+}
+
+#[inline(always)]
+fn fp_batch_scale(a: &mut [Fp], c: &Fp) {
+    for x in a.iter_mut() {
+        *x = fp_mul(x, c);
+    }
+}
 
-    fn to_block(self) -> Block {
-        unsafe { std::mem::transmute((self.0).0) }
+#[inline(always)]
+fn fp_batch_inverse(a: &mut [Fp]) -> Option<()> {
+    let mut prefix = Vec::with_capacity(a.len());
+    let mut acc = Fp::one();
+    for x in a.iter() {
+        prefix.push(acc);
+        acc = fp_mul(&acc, x);
     }
+    let mut inv = acc.inverse()?;
+    for (x, p) in a.iter_mut().zip(prefix).rev() {
+        let x_inv = fp_mul(&inv, &p);
+        inv = fp_mul(&inv, x);
+        *x = x_inv;
+    }
+    return Some(());
+}
+
+#[inline(always)]
+fn fp_to_u128(x: &Fp) -> u128 {
+    let limbs = (x.0).0;
+    return (limbs[1] as u128) << 64 | limbs[0] as u128;
 }
 
-impl From<Block> for Fp {
-    #[inline]
-    fn from(m: Block) -> Fp {
-        unsafe { Fp(FpRepr(*(&m as *const _ as *const [u64; 2]))) }
+#[inline(always)]
+fn fp_from_u128(x: u128) -> Fp {
+    Fp(FpRepr([x as u64, (x >> 64) as u64]))
+}
+
+// x mod q for x < 2q
+#[inline(always)]
+fn fp_reduce(x: u128) -> u128 {
+    let (diff, borrow) = x.overflowing_sub(FP_MODULUS_U128);
+    if borrow {
+        x
+    } else {
+        diff
     }
 }
 
+// a + b*c + carry, as (low, high) limbs
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+// Montgomery multiplication x*y/R mod q
+#[inline(always)]
+fn fp_mul(x: &Fp, y: &Fp) -> Fp {
+    let a = (x.0).0;
+    let b = (y.0).0;
+
+    // schoolbook product r0 + r1*2^64 + r2*2^128 + r3*2^192
+    let (r0, carry) = mac(0, a[0], b[0], 0);
+    let (r1, r2) = mac(0, a[0], b[1], carry);
+    let (r1, carry) = mac(r1, a[1], b[0], 0);
+    let (r2, r3) = mac(r2, a[1], b[1], carry);
+
+    // Montgomery reduction, one limb at a time
+    let k = r0.wrapping_mul(FP_INV);
+    let (_, carry) = mac(r0, k, FP_MODULUS[0], 0);
+    let (r1, carry) = mac(r1, k, FP_MODULUS[1], carry);
+    let (r2, carry2) = mac(r2, 1, carry, 0);
+    let k = r1.wrapping_mul(FP_INV);
+    let (_, carry) = mac(r1, k, FP_MODULUS[0], 0);
+    let (r2, carry) = mac(r2, k, FP_MODULUS[1], carry);
+    let r3 = r3.wrapping_add(carry2).wrapping_add(carry);
+
+    return fp_from_u128(fp_reduce((r3 as u128) << 64 | r2 as u128));
+}
+
 // impl From<Fp> for Block {
 //     #[inline]
 //     fn from(Fp(FpRepr(data)): Fp) -> Block {
@@ -93,18 +374,138 @@ impl From<Block> for Fp {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::field255::Fp255;
+    use crate::field64::Fp64;
     use rand::thread_rng;
 
     #[test]
-    fn test_fp_block_conversion() {
+    fn test_fp_constants() {
+        assert_eq!(Fp::A, 256);
+        assert_eq!(Fp::B, 2187);
+        assert_eq!(Fp::alpha().pow([Fp::A as u64]), Fp::one());
+        assert_ne!(Fp::alpha().pow([(Fp::A / 2) as u64]), Fp::one());
+        assert_eq!(Fp::beta().pow([Fp::B as u64]), Fp::one());
+        assert_ne!(Fp::beta().pow([(Fp::B / 3) as u64]), Fp::one());
+    }
+
+    #[test]
+    fn test_fp_encoding() {
+        check_encoding::<Fp>();
+        check_encoding::<Fp64>();
+        check_encoding::<Fp255>();
+    }
+
+    fn check_encoding<F: OleField>() {
         let mut rng = thread_rng();
+        let mut bytes = vec![0u8; F::BYTES];
         for _ in 0..1000 {
-            let v = Fp::random(&mut rng);
-            let v_ = v.clone();
-            let block: Block = v.to_block();
-            let v_from_block: Fp = block.into();
+            let v = F::random(&mut rng);
+            v.encode(&mut bytes);
+            assert_eq!(F::decode(&bytes).unwrap(), v);
+        }
+    }
 
-            assert_eq!(v_, v_from_block);
+    #[test]
+    fn test_batch_arithmetic() {
+        assert_eq!(Fp::char().0, FP_MODULUS);
+        check_batch_arithmetic::<Fp>();
+        check_batch_arithmetic::<Fp64>();
+
+        // the portable loops agree with the ones picked at runtime
+        let mut rng = thread_rng();
+        let a: Vec<Fp> = (0..100).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..100).map(|_| Fp::random(&mut rng)).collect();
+        let (mut x, mut y) = (a.to_vec(), a.to_vec());
+        fp_batch_mul(&mut x, &b);
+        Fp::batch_mul(&mut y, &b);
+        assert_eq!(x, y);
+        fp_batch_scale(&mut x, &b[0]);
+        Fp::batch_scale(&mut y, &b[0]);
+        assert_eq!(x, y);
+        fp_batch_inverse(&mut x).unwrap();
+        Fp::batch_inverse(&mut y).unwrap();
+        assert_eq!(x, y);
+    }
+
+    fn check_batch_arithmetic<F: OleField>() {
+        let mut rng = thread_rng();
+        for n in [0, 1, 2, 100].iter() {
+            let a: Vec<F> = (0..*n).map(|_| F::random(&mut rng)).collect();
+            let b: Vec<F> = (0..*n).map(|_| F::random(&mut rng)).collect();
+            let c = F::random(&mut rng);
+
+            let mut sum = a.to_vec();
+            F::batch_add(&mut sum, &b);
+            let mut diff = a.to_vec();
+            F::batch_sub(&mut diff, &b);
+            let mut prod = a.to_vec();
+            F::batch_mul(&mut prod, &b);
+            let mut scaled = a.to_vec();
+            F::batch_scale(&mut scaled, &c);
+            let mut inv = a.to_vec();
+            F::batch_inverse(&mut inv).unwrap();
+
+            for i in 0..*n {
+                let mut x = a[i];
+                x.add_assign(&b[i]);
+                assert_eq!(sum[i], x);
+                let mut x = a[i];
+                x.sub_assign(&b[i]);
+                assert_eq!(diff[i], x);
+                let mut x = a[i];
+                x.mul_assign(&b[i]);
+                assert_eq!(prod[i], x);
+                let mut x = a[i];
+                x.mul_assign(&c);
+                assert_eq!(scaled[i], x);
+                assert_eq!(inv[i], a[i].inverse().unwrap());
+            }
+        }
+
+        // edge cases of the reductions
+        let mut minus_one = F::zero();
+        minus_one.sub_assign(&F::one());
+        let mut x = vec![minus_one, F::zero(), minus_one];
+        F::batch_add(&mut x, &[minus_one, minus_one, F::one()]);
+        let mut minus_two = minus_one;
+        minus_two.double();
+        assert_eq!(x, vec![minus_two, minus_one, F::zero()]);
+        F::batch_sub(&mut x, &[minus_one, F::zero(), minus_one]);
+        assert_eq!(x, vec![minus_one, minus_one, F::one()]);
+        F::batch_mul(&mut x, &[minus_one, F::zero(), minus_one]);
+        assert_eq!(x, vec![F::one(), F::zero(), minus_one]);
+
+        // a zero is reported and leaves the slice untouched
+        let mut inv = x.to_vec();
+        assert_eq!(F::batch_inverse(&mut inv), None);
+        assert_eq!(inv, x);
+    }
+
+    #[test]
+    fn test_fp_strict_decoding() {
+        assert_eq!(Fp::BYTES, 16);
+        let mut bytes = [0u8; 16];
+        Fp::one().encode(&mut bytes);
+        let mut one = [0u8; 16];
+        one[0] = 1;
+        assert_eq!(bytes, one);
+
+        // q - 1 is the largest valid encoding, q and above are rejected
+        let mut q_minus_one = Fp::zero();
+        q_minus_one.sub_assign(&Fp::one());
+        let mut q = Fp::char();
+        q_minus_one.encode(&mut bytes);
+        assert_eq!(Fp::decode(&bytes).unwrap(), q_minus_one);
+
+        for (chunk, limb) in bytes.chunks_mut(8).zip(q.as_ref()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert!(Fp::decode(&bytes).is_err());
+        q.add_nocarry(&1.into());
+        for (chunk, limb) in bytes.chunks_mut(8).zip(q.as_ref()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
         }
+        assert!(Fp::decode(&bytes).is_err());
+        assert!(Fp::decode(&[0xff; 16]).is_err());
     }
 }
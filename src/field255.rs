@@ -0,0 +1,35 @@
+use crate::field::{BatchField, OleField};
+use ff::PrimeField;
+
+// Prime q = 28948022309329048855892746252171976963317496166410141009867009303988066582529,
+// with bit size 255
+// q = 2^32 * 3^12 * 12682474003299820579644313802952552905043619339936405208247973 + 1
+// generator of multiplicative group: 13
+//
+// Elements take two blocks, so OT messages are sent encrypted under OT'd keys
+#[derive(PrimeField, OleField)]
+#[PrimeFieldModulus = "28948022309329048855892746252171976963317496166410141009867009303988066582529"]
+#[PrimeFieldGenerator = "13"]
+#[OleFieldAlphaOrder = "256"]
+#[OleFieldBetaOrder = "2187"]
+#[OleFieldCrate = "crate"]
+pub struct Fp255(Fp255Repr);
+
+impl BatchField for Fp255 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::FiniteField;
+    use ff::Field;
+
+    #[test]
+    fn test_fp255_constants() {
+        assert_eq!(Fp255::NUM_BITS, 255);
+        assert_eq!(Fp255::BYTES, 32);
+        assert_eq!(Fp255::alpha().pow([Fp255::A as u64]), Fp255::one());
+        assert_ne!(Fp255::alpha().pow([(Fp255::A / 2) as u64]), Fp255::one());
+        assert_eq!(Fp255::beta().pow([Fp255::B as u64]), Fp255::one());
+        assert_ne!(Fp255::beta().pow([(Fp255::B / 3) as u64]), Fp255::one());
+    }
+}
@@ -0,0 +1,303 @@
+use crate::field::{BatchField, OleField};
+use ff::{Field, PrimeField, PrimeFieldDecodingError, PrimeFieldRepr};
+use rand_core::RngCore;
+use std::fmt;
+
+// Prime q = 9223197695995281409, with bit size 63
+// q = 2^24 * 3^12 * 1034443 + 1
+// generator of multiplicative group: 7
+//
+// Elements are kept in Montgomery form x*R mod q with R = 2^64, so a multiplication is
+// a single 64x64 -> 128 bit product and one reduction. As q < 2^63, sums of two reduced
+// elements and the intermediate values of the reduction don't overflow.
+const MODULUS: u64 = 0x7fff61701b000001;
+// -q^-1 mod 2^64
+const INV: u64 = 0xdd2661701affffff;
+// R mod q
+const R: u64 = 0x00013d1fc9fffffe;
+// R^2 mod q
+const R2: u64 = 0x54dac8c7434cb64b;
+const GENERATOR: u64 = 7;
+// GENERATOR^((q-1)/2^24)
+const ROOT_OF_UNITY: u64 = 0x69217ce42473e4b9;
+// elements of order 2^8 and 3^7
+const ALPHA: u64 = 0x03ec889a2a3bfcfc;
+const BETA: u64 = 0x173b839a3158dbdc;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+pub struct Fp64Repr(pub [u64; 1]);
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Fp64(u64);
+
+// x*y/R mod q for t = x*y < q^2
+#[inline(always)]
+fn mont_reduce(t: u128) -> u64 {
+    let m = (t as u64).wrapping_mul(INV);
+    let u = ((t + (m as u128) * (MODULUS as u128)) >> 64) as u64;
+    if u >= MODULUS {
+        u - MODULUS
+    } else {
+        u
+    }
+}
+
+impl Fp64 {
+    // from an integer in [0, q)
+    #[inline]
+    fn from_canonical(x: u64) -> Self {
+        Fp64(mont_reduce((x as u128) * (R2 as u128)))
+    }
+
+    #[inline]
+    fn to_canonical(self) -> u64 {
+        mont_reduce(self.0 as u128)
+    }
+}
+
+impl fmt::Display for Fp64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Fp64({})", self.into_repr())
+    }
+}
+
+impl fmt::Debug for Fp64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Fp64({})", self.into_repr())
+    }
+}
+
+impl Field for Fp64 {
+    fn random<Rng: RngCore + ?Sized>(rng: &mut Rng) -> Self {
+        loop {
+            let x = rng.next_u64() >> 1;
+            if x < MODULUS {
+                return Fp64(x);
+            }
+        }
+    }
+
+    fn zero() -> Self {
+        Fp64(0)
+    }
+
+    fn one() -> Self {
+        Fp64(R)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    fn square(&mut self) {
+        let x = *self;
+        self.mul_assign(&x);
+    }
+
+    fn double(&mut self) {
+        let x = *self;
+        self.add_assign(&x);
+    }
+
+    fn negate(&mut self) {
+        if self.0 != 0 {
+            self.0 = MODULUS - self.0;
+        }
+    }
+
+    #[inline]
+    fn add_assign(&mut self, other: &Self) {
+        let sum = self.0 + other.0;
+        self.0 = if sum >= MODULUS { sum - MODULUS } else { sum };
+    }
+
+    #[inline]
+    fn sub_assign(&mut self, other: &Self) {
+        self.0 = if self.0 >= other.0 {
+            self.0 - other.0
+        } else {
+            self.0 + MODULUS - other.0
+        };
+    }
+
+    #[inline]
+    fn mul_assign(&mut self, other: &Self) {
+        self.0 = mont_reduce((self.0 as u128) * (other.0 as u128));
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.pow([MODULUS - 2]))
+        }
+    }
+
+    fn frobenius_map(&mut self, _: usize) {}
+}
+
+impl PrimeField for Fp64 {
+    type Repr = Fp64Repr;
+
+    fn from_repr(repr: Fp64Repr) -> Result<Self, PrimeFieldDecodingError> {
+        if repr.0[0] >= MODULUS {
+            return Err(PrimeFieldDecodingError::NotInField(format!("{}", repr)));
+        }
+        Ok(Fp64::from_canonical(repr.0[0]))
+    }
+
+    fn into_repr(&self) -> Fp64Repr {
+        Fp64Repr([self.to_canonical()])
+    }
+
+    fn char() -> Fp64Repr {
+        Fp64Repr([MODULUS])
+    }
+
+    const NUM_BITS: u32 = 63;
+    const CAPACITY: u32 = 62;
+
+    fn multiplicative_generator() -> Self {
+        Fp64::from_canonical(GENERATOR)
+    }
+
+    const S: u32 = 24;
+
+    fn root_of_unity() -> Self {
+        Fp64::from_canonical(ROOT_OF_UNITY)
+    }
+}
+
+impl OleField for Fp64 {
+    const A: usize = 256usize; //2usize.pow(8);
+    const B: usize = 2187; //3usize.pow(7);
+
+    fn alpha() -> Self {
+        Fp64::from_canonical(ALPHA)
+    }
+
+    fn beta() -> Self {
+        Fp64::from_canonical(BETA)
+    }
+}
+
+impl BatchField for Fp64 {}
+
+impl From<Fp64> for Fp64Repr {
+    fn from(x: Fp64) -> Fp64Repr {
+        x.into_repr()
+    }
+}
+
+impl From<u64> for Fp64Repr {
+    fn from(x: u64) -> Fp64Repr {
+        Fp64Repr([x])
+    }
+}
+
+impl AsRef<[u64]> for Fp64Repr {
+    fn as_ref(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+impl AsMut<[u64]> for Fp64Repr {
+    fn as_mut(&mut self) -> &mut [u64] {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for Fp64Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:016x}", self.0[0])
+    }
+}
+
+impl PrimeFieldRepr for Fp64Repr {
+    fn sub_noborrow(&mut self, other: &Self) {
+        self.0[0] = self.0[0].wrapping_sub(other.0[0]);
+    }
+
+    fn add_nocarry(&mut self, other: &Self) {
+        self.0[0] = self.0[0].wrapping_add(other.0[0]);
+    }
+
+    fn num_bits(&self) -> u32 {
+        64 - self.0[0].leading_zeros()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0[0] == 0
+    }
+
+    fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    fn is_even(&self) -> bool {
+        !self.is_odd()
+    }
+
+    fn div2(&mut self) {
+        self.0[0] >>= 1;
+    }
+
+    fn shr(&mut self, amt: u32) {
+        self.0[0] = self.0[0].checked_shr(amt).unwrap_or(0);
+    }
+
+    fn mul2(&mut self) {
+        self.0[0] <<= 1;
+    }
+
+    fn shl(&mut self, amt: u32) {
+        self.0[0] = self.0[0].checked_shl(amt).unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_fp64_arithmetic() {
+        let mut rng = thread_rng();
+        let q = MODULUS as u128;
+        for _ in 0..1000 {
+            let a = Fp64::random(&mut rng);
+            let b = Fp64::random(&mut rng);
+            let (x, y) = (a.to_canonical() as u128, b.to_canonical() as u128);
+
+            let mut sum = a;
+            sum.add_assign(&b);
+            assert_eq!(sum.to_canonical() as u128, (x + y) % q);
+
+            let mut diff = a;
+            diff.sub_assign(&b);
+            assert_eq!(diff.to_canonical() as u128, (x + q - y) % q);
+
+            let mut prod = a;
+            prod.mul_assign(&b);
+            assert_eq!(prod.to_canonical() as u128, (x * y) % q);
+
+            if !a.is_zero() {
+                let mut one = a.inverse().unwrap();
+                one.mul_assign(&a);
+                assert_eq!(one, Fp64::one());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fp64_constants() {
+        assert_eq!(Fp64::one().to_canonical(), 1);
+        assert_eq!(Fp64::from_canonical(R2).to_canonical(), R2);
+        assert_eq!(Fp64::alpha().pow([Fp64::A as u64]), Fp64::one());
+        assert_ne!(Fp64::alpha().pow([(Fp64::A / 2) as u64]), Fp64::one());
+        assert_eq!(Fp64::beta().pow([Fp64::B as u64]), Fp64::one());
+        assert_ne!(Fp64::beta().pow([(Fp64::B / 3) as u64]), Fp64::one());
+        assert_eq!(Fp64::root_of_unity().pow([1 << 24]), Fp64::one());
+        assert_ne!(Fp64::root_of_unity().pow([1 << 23]), Fp64::one());
+    }
+}
@@ -1,11 +1,26 @@
+// explicit returns and field names are the style of this crate
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+pub mod async_ole;
+pub mod error;
+pub mod extension;
 pub mod fft;
 pub mod field;
+pub mod field255;
+pub mod field64;
 pub mod ole;
+pub mod params;
 pub mod poly;
+pub mod prg;
 pub mod shamir;
+pub mod triples;
+pub mod vole;
 
 mod encoding;
-mod error;
+mod ot_pool;
+
+#[cfg(test)]
+mod test_utils;
 
 // #[cfg(test)]
 // mod field;
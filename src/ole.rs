@@ -1,12 +1,15 @@
 use crate::encoding;
 use crate::error::OleError;
 use crate::field::OleField;
+use crate::ot_pool::{self, ReceiverPool, SenderPool};
+use crate::params::OleParams;
 use crate::poly;
+use crate::prg::expand_field_vec;
 use crate::shamir;
-use ff::PrimeFieldRepr;
+use ff::PrimeFieldDecodingError;
 use ocelot::ot::{KosReceiver, KosSender, Receiver as OTReceiver, Sender as OTSender};
-use rand::{CryptoRng, Rng};
-use scuttlebutt::{channel::AbstractChannel, Block};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use scuttlebutt::{channel::AbstractChannel, AesRng, Block};
 use sha2::{Digest, Sha256};
 // use itertools::interleave;
 
@@ -20,17 +23,47 @@ where
         rng: &mut Crng,
     ) -> Result<Self, OleError>;
 
+    // runs OLE with the default parameters of the field
     fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
         &mut self,
         a: &[F],
         b: &[F],
         c: &mut C,
         rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        self.input_with_params(a, b, &OleParams::default(), c, rng)
+    }
+
+    // the receiver must use the same parameters
+    fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        params: &OleParams<F>,
+        c: &mut C,
+        rng: &mut Crng,
     ) -> Result<(), OleError>;
+
+    // offline phase: runs n OLEs on random inputs a, b, to be derandomized later by
+    // RandomOleSender::input once the actual inputs are known
+    fn input_random<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<RandomOleSender<F>, OleError> {
+        let a: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        self.input(&a, &b, channel, rng)?;
+        Ok(RandomOleSender::new(a, b))
+    }
 }
 
 pub struct OleSender {
-    ot: KosSender,
+    // None when restored from a saved pool, until precompute runs the base OTs again
+    ot: Option<KosSender>,
+    // precomputed OTs, used before OT-extension
+    pool: SenderPool,
 }
 
 impl Sender for OleSender {
@@ -39,83 +72,181 @@ impl Sender for OleSender {
         rng: &mut Crng,
     ) -> Result<Self, OleError> {
         let ot = KosSender::init(channel, rng)?;
-        Ok(Self { ot: ot })
+        Ok(Self {
+            ot: Some(ot),
+            pool: SenderPool::default(),
+        })
     }
 
-    fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+    // Inputs of any length are split into instances of params.batch_size() OLEs each,
+    // the last instance is padded with random values.
+    fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
         &mut self,
         a: &[F],
         b: &[F],
+        params: &OleParams<F>,
         channel: &mut C,
         rng: &mut Crng,
     ) -> Result<(), OleError> {
         assert_eq!(a.len(), b.len());
+        self.check_pool(a.len(), params)?;
 
-        let mask: Vec<F> = (0..F::B).map(|_| F::random(rng)).collect();
-        let secret = F::random(rng);
-        let shares: Vec<F> = shamir::share(&secret, F::B as u64, (F::B - F::A) as u64, &F::beta());
+        let n = params.batch_size();
+        let instances: Vec<(&[F], &[F])> = a.chunks(n).zip(b.chunks(n)).collect();
+        for round in instances.chunks(ROUND_INSTANCES) {
+            self.input_round(round, params, channel, rng)?;
+        }
+        return Ok(());
+    }
+}
 
-        let mut hasher = Sha256::new();
-        secret.into_repr().write_be(&mut hasher)?;
+impl OleSender {
+    // Precomputes the random OTs for n OLEs with the default parameters of the field
+    pub fn precompute<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        self.precompute_with_params(n, &OleParams::<F>::default(), channel, rng)
+    }
 
-        let com = hasher.finalize();
-        channel.write_bytes(com.as_ref())?;
-        channel.flush()?;
+    // Precomputes the random OTs for n OLEs with params by OT-extension, adding them to
+    // the pool, which is used up before running OT-extension again and is what
+    // save_pool persists. Refills the pool of a restored sender, running the base OTs
+    // again first. The receiver must precompute the same number.
+    pub fn precompute_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        return self.precompute_ots(num_ots(n, params), channel, rng);
+    }
 
-        let shares_blocks = shares.iter().map(|share| share.to_block());
-        let mask_blocks = mask.iter().map(|mask_elem| mask_elem.to_block());
-        let ot_input: Vec<(Block, Block)> = shares_blocks.zip(mask_blocks).collect();
-        self.ot.send(channel, ot_input.as_slice(), rng)?;
-
-        let v_blocks = channel.read_blocks(F::B)?;
-
-        let mut alpha2 = F::alpha();
-        alpha2.square();
-
-        let mut a_poly = a.to_vec();
-        a_poly.resize_with(F::A / 2, || F::random(rng));
-        F::fft2_inverse(&mut a_poly, &alpha2);
-        let mut a_vals = a_poly.to_vec();
-        a_vals.resize_with(F::B, F::zero);
-        F::fft3(&mut a_vals, &F::beta());
-
-        let mut b_poly = encoding::pad_every_other(&b, rng);
-        b_poly.resize_with(F::A, || F::random(rng));
-        F::fft2_inverse(&mut b_poly, &F::alpha());
-        let mut b_vals = b_poly.to_vec();
-        b_vals.resize_with(F::B, F::zero);
-        F::fft3(&mut b_vals, &F::beta());
-
-        for (i, v) in v_blocks.iter().enumerate() {
-            a_vals[i].mul_assign(&(*v).into());
-            a_vals[i].add_assign(&b_vals[i]);
-            a_vals[i].add_assign(&mask[i]);
+    // adds num random OTs to the pool
+    pub(crate) fn precompute_ots<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        num: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        if self.ot.is_none() {
+            self.ot = Some(KosSender::init(channel, rng)?);
         }
+        let ots: Vec<(Block, Block)> = (0..num)
+            .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+            .collect();
+        self.ot
+            .as_mut()
+            .unwrap()
+            .send(channel, ots.as_slice(), rng)?;
+        self.pool.extend(ots);
+        return Ok(());
+    }
+
+    // Serializes the remaining precomputed OTs, bound to session_id, as the next
+    // generation of the pool. Consumes the sender, so the saved OTs can't also be used
+    // by it.
+    //
+    // Only this fixed-size pool is saved: ocelot keeps the OT-extension state private,
+    // see ot_pool.
+    pub fn save_pool(mut self, session_id: &Block) -> Vec<u8> {
+        return self.take_pool(session_id);
+    }
+
+    // saves the pool like save_pool, leaving this one empty
+    pub(crate) fn take_pool(&mut self, session_id: &Block) -> Vec<u8> {
+        return self.pool.save(session_id);
+    }
+
+    // Restores a sender from a saved pool without running base OTs. Fails with
+    // SessionMismatch unless the receiver restored state of the same session, generation
+    // and progress. On success bytes are marked as restored, and restoring them again
+    // fails with StateReplayed, so store them back before using the sender. Input
+    // fails with OTPoolExhausted, without touching the channel, when the pool doesn't
+    // hold the OTs it needs, until precompute refills it.
+    pub fn restore_pool<C: AbstractChannel>(
+        bytes: &mut [u8],
+        channel: &mut C,
+    ) -> Result<Self, OleError> {
+        let (session_id, pool) = SenderPool::restore(bytes)?;
+        ot_pool::check_session(channel, &session_id, pool.generation(), pool.len())?;
+        ot_pool::invalidate(bytes);
+        Ok(Self {
+            ot: None,
+            pool: pool,
+        })
+    }
 
-        for w in a_vals.iter() {
-            channel.write_block(&w.to_block())?;
+    // fails with OTPoolExhausted when a restored sender doesn't hold the OTs of n OLEs
+    pub(crate) fn check_pool<F: OleField>(
+        &self,
+        n: usize,
+        params: &OleParams<F>,
+    ) -> Result<(), OleError> {
+        if self.ot.is_none() && self.pool.len() < num_ots(n, params) {
+            return Err(OleError::OTPoolExhausted);
+        }
+        return Ok(());
+    }
+
+    // takes as many OTs as the pool holds, and the rest from OT-extension
+    pub(crate) fn ot_send<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        let pooled = self.pool.len().min(inputs.len());
+        if pooled < inputs.len() && self.ot.is_none() {
+            return Err(OleError::OTPoolExhausted);
+        }
+        if pooled > 0 {
+            self.pool.send(channel, &inputs[..pooled])?;
+        }
+        if pooled < inputs.len() {
+            self.ot
+                .as_mut()
+                .unwrap()
+                .send(channel, &inputs[pooled..], rng)?;
         }
+        return Ok(());
+    }
+
+    // runs OLE instances on at most params.batch_size() inputs (a, b) each in lockstep,
+    // see SenderRound
+    fn input_round<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        instances: &[(&[F], &[F])],
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        let mut round = SenderRound::commit(instances, params, rng)?;
+        channel.write_bytes(&round.commitments())?;
         channel.flush()?;
 
-        let zr = (channel.read_block()?).into();
-        let a_zr = poly::horner(&a_poly, &zr);
-        let b_zr = poly::horner(&b_poly, &zr);
-        let zs = F::random(rng);
+        for ot_input in round.ot_inputs() {
+            self.ot_send(channel, ot_input, rng)?;
+        }
+        channel.write_bytes(&round.ot_payloads())?;
+        channel.flush()?;
 
-        channel.write_block(&a_zr.to_block())?;
-        channel.write_block(&b_zr.to_block())?;
-        channel.write_block(&zs.to_block())?;
+        let mut vs = vec![0u8; round.encodings_len()];
+        channel.read_bytes(&mut vs)?;
+        channel.write_bytes(&round.respond(&vs)?)?;
         channel.flush()?;
 
-        let mut ax_b = poly::horner(&a_poly, &zs);
-        let b_zs = poly::horner(&b_poly, &zs);
-        let x_zs: F = (channel.read_block()?).into();
-        let y_zs: F = (channel.read_block()?).into();
-        ax_b.mul_assign(&x_zs);
-        ax_b.add_assign(&b_zs);
-        assert_eq!(ax_b, y_zs);
+        let mut zr_seeds = vec![0u8; round.challenges_len()];
+        channel.read_bytes(&mut zr_seeds)?;
+        channel.write_bytes(&round.open(&zr_seeds, rng))?;
+        channel.flush()?;
 
-        return Ok(());
+        let mut evaluations = vec![0u8; round.evaluations_len()];
+        channel.read_bytes(&mut evaluations)?;
+        return round.check(&evaluations);
     }
 }
 
@@ -129,16 +260,44 @@ where
         rng: &mut Crng,
     ) -> Result<Self, OleError>;
 
+    // runs OLE with the default parameters of the field
     fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
         &mut self,
         x: &[F],
         channel: &mut C,
         rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError> {
+        self.input_with_params(x, &OleParams::default(), channel, rng)
+    }
+
+    // the sender must use the same parameters
+    fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        x: &[F],
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
     ) -> Result<Vec<F>, OleError>;
+
+    // offline phase: runs n OLEs on random inputs x, to be derandomized later by
+    // RandomOleReceiver::input once the actual inputs are known
+    fn input_random<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<RandomOleReceiver<F>, OleError> {
+        let x: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let y = self.input(&x, channel, rng)?;
+        Ok(RandomOleReceiver::new(x, y))
+    }
 }
 
 pub struct OleReceiver {
-    ot: KosReceiver,
+    // None when restored from a saved pool, until precompute runs the base OTs again
+    ot: Option<KosReceiver>,
+    // precomputed OTs, used before OT-extension
+    pool: ReceiverPool,
 }
 
 impl Receiver for OleReceiver {
@@ -147,147 +306,994 @@ impl Receiver for OleReceiver {
         rng: &mut Crng,
     ) -> Result<Self, OleError> {
         let ot = KosReceiver::init(channel, rng)?;
-        Ok(Self { ot: ot })
+        Ok(Self {
+            ot: Some(ot),
+            pool: ReceiverPool::default(),
+        })
     }
 
-    fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+    // Inputs of any length are split into instances of params.batch_size() OLEs each,
+    // the last instance is padded with random values. Returns exactly x.len() outputs.
+    fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
         &mut self,
         x: &[F],
+        params: &OleParams<F>,
         channel: &mut C,
         rng: &mut Crng,
     ) -> Result<Vec<F>, OleError> {
-        let mut com = [0u8; 32];
-        channel.read_bytes(&mut com)?;
-
-        let (encoded, x_poly, indices) = encoding::encode_reed_solomon(&x, rng);
-
-        let mut share_indices = vec![];
-        let mut j = 0;
-        for i in 0..F::B {
-            if (j < F::A as usize) && (i == indices[j]) {
-                j += 1;
-            } else {
-                share_indices.push(i);
+        self.check_pool(x.len(), params)?;
+
+        let mut result = Vec::with_capacity(x.len());
+        let instances: Vec<&[F]> = x.chunks(params.batch_size()).collect();
+        for round in instances.chunks(ROUND_INSTANCES) {
+            let ys = self.input_round(round, params, channel, rng)?;
+            for (x_chunk, mut y) in round.iter().zip(ys) {
+                y.truncate(x_chunk.len());
+                result.append(&mut y);
             }
         }
+        return Ok(result);
+    }
+}
 
-        let mut j = 0;
-        let choices: Vec<bool> = (0..F::B)
-            .map(|i| {
-                if (j < F::A as usize) && (i == indices[j]) {
-                    j += 1;
-                    true
-                } else {
-                    false
-                }
-            })
-            .collect();
-        let vals = self.ot.receive(channel, &choices, rng)?;
+impl OleReceiver {
+    // Precomputes the random OTs for n OLEs with the default parameters of the field
+    pub fn precompute<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        self.precompute_with_params(n, &OleParams::<F>::default(), channel, rng)
+    }
+
+    // Precomputes the random OTs for n OLEs with params by OT-extension, adding them to
+    // the pool, which is used up before running OT-extension again and is what
+    // save_pool persists. Refills the pool of a restored receiver, running the base
+    // OTs again first. The sender must precompute the same number.
+    pub fn precompute_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        return self.precompute_ots(num_ots(n, params), channel, rng);
+    }
+
+    // adds num random OTs to the pool
+    pub(crate) fn precompute_ots<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        num: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        if self.ot.is_none() {
+            self.ot = Some(KosReceiver::init(channel, rng)?);
+        }
+        let choices: Vec<bool> = (0..num).map(|_| rng.gen::<bool>()).collect();
+        let ots = self.ot.as_mut().unwrap().receive(channel, &choices, rng)?;
+        self.pool.extend(choices, ots);
+        return Ok(());
+    }
+
+    // Serializes the remaining precomputed OTs, bound to session_id, as the next
+    // generation of the pool. Consumes the receiver, so the saved OTs can't also be used
+    // by it.
+    //
+    // Only this fixed-size pool is saved: ocelot keeps the OT-extension state private,
+    // see ot_pool.
+    pub fn save_pool(mut self, session_id: &Block) -> Vec<u8> {
+        return self.take_pool(session_id);
+    }
 
-        let mask: Vec<F> = indices.iter().map(|i| vals[*i as usize].into()).collect();
-        let shares: Vec<F> = share_indices
+    // saves the pool like save_pool, leaving this one empty
+    pub(crate) fn take_pool(&mut self, session_id: &Block) -> Vec<u8> {
+        return self.pool.save(session_id);
+    }
+
+    // Restores a receiver from a saved pool without running base OTs. Fails with
+    // SessionMismatch unless the sender restored state of the same session, generation
+    // and progress. On success bytes are marked as restored, and restoring them again
+    // fails with StateReplayed, so store them back before using the receiver. Input
+    // fails with OTPoolExhausted, without touching the channel, when the pool doesn't
+    // hold the OTs it needs, until precompute refills it.
+    pub fn restore_pool<C: AbstractChannel>(
+        bytes: &mut [u8],
+        channel: &mut C,
+    ) -> Result<Self, OleError> {
+        let (session_id, pool) = ReceiverPool::restore(bytes)?;
+        ot_pool::check_session(channel, &session_id, pool.generation(), pool.len())?;
+        ot_pool::invalidate(bytes);
+        Ok(Self {
+            ot: None,
+            pool: pool,
+        })
+    }
+
+    // fails with OTPoolExhausted when a restored receiver doesn't hold the OTs of n OLEs
+    pub(crate) fn check_pool<F: OleField>(
+        &self,
+        n: usize,
+        params: &OleParams<F>,
+    ) -> Result<(), OleError> {
+        if self.ot.is_none() && self.pool.len() < num_ots(n, params) {
+            return Err(OleError::OTPoolExhausted);
+        }
+        return Ok(());
+    }
+
+    // takes as many OTs as the pool holds, and the rest from OT-extension
+    pub(crate) fn ot_receive<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        choices: &[bool],
+        rng: &mut Crng,
+    ) -> Result<Vec<Block>, OleError> {
+        let pooled = self.pool.len().min(choices.len());
+        if pooled < choices.len() && self.ot.is_none() {
+            return Err(OleError::OTPoolExhausted);
+        }
+        let mut vals = if pooled > 0 {
+            self.pool.receive(channel, &choices[..pooled])?
+        } else {
+            Vec::with_capacity(choices.len())
+        };
+        if pooled < choices.len() {
+            let ot = self.ot.as_mut().unwrap();
+            vals.append(&mut ot.receive(channel, &choices[pooled..], rng)?);
+        }
+        return Ok(vals);
+    }
+
+    // runs OLE instances on at most params.batch_size() inputs x each in lockstep, see
+    // ReceiverRound. Returns the outputs of each instance
+    fn input_round<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        instances: &[&[F]],
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<Vec<F>>, OleError> {
+        let mut round = ReceiverRound::encode(instances, params, rng);
+        let mut coms = vec![0u8; round.commitments_len()];
+        channel.read_bytes(&mut coms)?;
+
+        let mut vals = Vec::with_capacity(instances.len());
+        for choices in round.ot_choices() {
+            vals.push(self.ot_receive(channel, choices, rng)?);
+        }
+        let mut ot_payloads = vec![0u8; round.ot_payloads_len()];
+        channel.read_bytes(&mut ot_payloads)?;
+        channel.write_bytes(&round.open(&coms, vals, &ot_payloads)?)?;
+        channel.flush()?;
+
+        let mut wss = vec![0u8; round.responses_len()];
+        channel.read_bytes(&mut wss)?;
+        channel.write_bytes(&round.decode(&wss, rng)?)?;
+        channel.flush()?;
+
+        let mut openings = vec![0u8; round.openings_len()];
+        channel.read_bytes(&mut openings)?;
+        channel.write_bytes(&round.check(&openings)?)?;
+        channel.flush()?;
+
+        return Ok(round.output());
+    }
+}
+
+// Instances of a large batch run in lockstep rounds of ROUND_INSTANCES instances, whose
+// computation is spread over all cores with the parallel feature. It fixes the order of
+// the messages, so both parties must use the same value.
+pub(crate) const ROUND_INSTANCES: usize = 64;
+
+// an independent rng for each of n instances, seeded from rng
+fn instance_rngs<Crng: CryptoRng + Rng>(n: usize, rng: &mut Crng) -> Vec<AesRng> {
+    return (0..n)
+        .map(|_| AesRng::from_seed(rng.gen::<Block>()))
+        .collect();
+}
+
+// maps f over items, concurrently with the parallel feature
+fn par_map<T: Send, U: Send, M: Fn(T) -> U + Sync + Send>(items: Vec<T>, f: M) -> Vec<U> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        return items.into_par_iter().map(f).collect();
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        return items.into_iter().map(f).collect();
+    }
+}
+
+// number of OTs used by n OLEs with params, code_length for each instance
+pub(crate) fn num_ots<F: OleField>(n: usize, params: &OleParams<F>) -> usize {
+    let instances = n.div_ceil(params.batch_size());
+    return instances * params.code_length();
+}
+
+// The computation of a round of the sender, between the messages of the round.
+// OleSender::input_round and AsyncOleSender drive it over their channels, so both
+// send the same messages in the same order:
+//   -> the commitments of all instances
+//   -> the OTs of each instance, then the OT payloads of all instances
+//   <- the receivers encodings v
+//   -> the responses w
+//   <- the receivers challenge seeds
+//   -> a(z), b(z) at the receivers challenges z and a challenge seed, per instance
+//   <- x(z), y(z) at the senders challenges
+pub(crate) struct SenderRound<'a, F: OleField> {
+    instances: &'a [(&'a [F], &'a [F])],
+    params: &'a OleParams<F>,
+    rngs: Vec<AesRng>,
+    commits: Vec<Commit<F>>,
+    // (a_poly, b_poly) of each instance
+    polys: Vec<(Vec<F>, Vec<F>)>,
+    zs_seeds: Vec<Block>,
+}
+
+impl<'a, F: OleField> SenderRound<'a, F> {
+    // commits to a random secret for each instance, computed with par_map as all the
+    // following steps
+    pub(crate) fn commit<Crng: CryptoRng + Rng>(
+        instances: &'a [(&'a [F], &'a [F])],
+        params: &'a OleParams<F>,
+        rng: &mut Crng,
+    ) -> Result<Self, OleError> {
+        let mut rngs = instance_rngs(instances.len(), rng);
+        let commits = par_map(rngs.iter_mut().collect(), |rng| sender_commit(params, rng))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            instances: instances,
+            params: params,
+            rngs: rngs,
+            commits: commits,
+            polys: vec![],
+            zs_seeds: vec![],
+        })
+    }
+
+    pub(crate) fn commitments(&self) -> Vec<u8> {
+        return self.commits.iter().flat_map(|c| c.0.clone()).collect();
+    }
+
+    // the OT inputs of each instance
+    pub(crate) fn ot_inputs(&self) -> impl Iterator<Item = &[(Block, Block)]> {
+        return self.commits.iter().map(|c| c.1.as_slice());
+    }
+
+    pub(crate) fn ot_payloads(&self) -> Vec<u8> {
+        return self.commits.iter().flat_map(|c| c.2.clone()).collect();
+    }
+
+    pub(crate) fn encodings_len(&self) -> usize {
+        return self.instances.len() * self.params.code_length() * F::BYTES;
+    }
+
+    // the responses w to the receivers encodings v
+    pub(crate) fn respond(&mut self, vs: &[u8]) -> Result<Vec<u8>, OleError> {
+        let vs: Vec<F> = to_elements(vs)?;
+        let params = self.params;
+        let responses = par_map(
+            self.instances
+                .iter()
+                .zip(vs.chunks(params.code_length()))
+                .zip(&self.commits)
+                .zip(self.rngs.iter_mut())
+                .collect(),
+            |((((a, b), v), (_, _, _, mask)), rng)| sender_response(a, b, v, mask, params, rng),
+        );
+        let mut wss = Vec::with_capacity(vs.len());
+        for (a_poly, b_poly, mut ws) in responses {
+            wss.append(&mut ws);
+            self.polys.push((a_poly, b_poly));
+        }
+        return Ok(to_bytes(&wss));
+    }
+
+    pub(crate) fn challenges_len(&self) -> usize {
+        return self.instances.len() * 16;
+    }
+
+    // evaluates a(x), b(x) at the challenges expanded from the receivers zr_seeds, and
+    // picks the seeds of the senders challenges
+    pub(crate) fn open<Crng: CryptoRng + Rng>(
+        &mut self,
+        zr_seeds: &[u8],
+        rng: &mut Crng,
+    ) -> Vec<u8> {
+        let k = self.params.challenges();
+        self.zs_seeds = self.instances.iter().map(|_| rng.gen::<Block>()).collect();
+        let evaluations = par_map(
+            self.polys.iter().zip(zr_seeds.chunks(16)).collect(),
+            |((a_poly, b_poly), zr_seed)| {
+                let zr: Vec<F> = expand_field_vec(&to_seed(zr_seed), k);
+                (evaluate(a_poly, &zr), evaluate(b_poly, &zr))
+            },
+        );
+        let mut bytes = Vec::with_capacity(self.evaluations_len() + self.challenges_len());
+        for ((a_zr, b_zr), zs_seed) in evaluations.iter().zip(&self.zs_seeds) {
+            bytes.extend(to_bytes(a_zr));
+            bytes.extend(to_bytes(b_zr));
+            bytes.extend_from_slice(zs_seed.as_ref());
+        }
+        return bytes;
+    }
+
+    pub(crate) fn evaluations_len(&self) -> usize {
+        return self.instances.len() * 2 * self.params.challenges() * F::BYTES;
+    }
+
+    // checks the receivers x(z), y(z) at the senders challenges
+    pub(crate) fn check(&self, evaluations: &[u8]) -> Result<(), OleError> {
+        let k = self.params.challenges();
+        let evaluations: Vec<F> = to_elements(evaluations)?;
+        par_map(
+            self.polys
+                .iter()
+                .zip(&self.zs_seeds)
+                .zip(evaluations.chunks(2 * k))
+                .collect(),
+            |(((a_poly, b_poly), zs_seed), xy_zs)| {
+                let zs: Vec<F> = expand_field_vec(zs_seed, k);
+                let (x_zs, y_zs) = xy_zs.split_at(k);
+                sender_check(a_poly, b_poly, &zs, x_zs, y_zs)
+            },
+        )
+        .into_iter()
+        .collect::<Result<Vec<()>, _>>()?;
+        return Ok(());
+    }
+}
+
+// The computation of a round of the receiver, between the messages of the round, see
+// SenderRound. Driven by OleReceiver::input_round and AsyncOleReceiver
+pub(crate) struct ReceiverRound<'a, F: OleField> {
+    params: &'a OleParams<F>,
+    // (encoding, x_poly, indices) of each instance
+    encodings: Vec<(Vec<F>, Vec<F>, Vec<usize>)>,
+    // (choices, share_indices) of each instance
+    choices: Vec<(Vec<bool>, Vec<usize>)>,
+    masks: Vec<Vec<F>>,
+    y_polys: Vec<Vec<F>>,
+    zr_seeds: Vec<Block>,
+}
+
+impl<'a, F: OleField> ReceiverRound<'a, F> {
+    // encodes the inputs x of each instance
+    pub(crate) fn encode<Crng: CryptoRng + Rng>(
+        instances: &[&[F]],
+        params: &'a OleParams<F>,
+        rng: &mut Crng,
+    ) -> Self {
+        let mut rngs = instance_rngs(instances.len(), rng);
+        let encodings = par_map(
+            instances.iter().zip(rngs.iter_mut()).collect(),
+            |(x, rng)| encoding::encode_reed_solomon(x, params, rng),
+        );
+        let choices = encodings
             .iter()
-            .map(|i| vals[*i as usize].into())
+            .map(|(_, _, indices)| receiver_choices(indices, params.code_length()))
             .collect();
+        Self {
+            params: params,
+            encodings: encodings,
+            choices: choices,
+            masks: vec![],
+            y_polys: vec![],
+            zr_seeds: vec![],
+        }
+    }
 
-        // check that commitment is correct
-        let secret = shamir::reconstruct(
-            &share_indices,
-            &shares,
-            F::B as u64,
-            (F::B - F::A) as u64,
-            &F::beta(),
+    pub(crate) fn commitments_len(&self) -> usize {
+        return self.encodings.len() * 32;
+    }
+
+    // the OT choices of each instance
+    pub(crate) fn ot_choices(&self) -> impl Iterator<Item = &[bool]> {
+        return self.choices.iter().map(|c| c.0.as_slice());
+    }
+
+    pub(crate) fn ot_payloads_len(&self) -> usize {
+        return self.encodings.len() * ot_payload_len::<F>(self.params.code_length());
+    }
+
+    // opens the senders commitments with the shares received by the OTs, and returns
+    // the encodings v
+    pub(crate) fn open(
+        &mut self,
+        coms: &[u8],
+        vals: Vec<Vec<Block>>,
+        ot_payloads: &[u8],
+    ) -> Result<Vec<u8>, OleError> {
+        let params = self.params;
+        let payload_len = ot_payload_len::<F>(params.code_length());
+        self.masks = par_map(
+            coms.chunks(32)
+                .zip(&vals)
+                .enumerate()
+                .map(|(i, x)| (x, &ot_payloads[i * payload_len..(i + 1) * payload_len]))
+                .zip(self.encodings.iter().zip(&self.choices))
+                .collect(),
+            |(((com, vals), ot_payload), ((_, _, indices), (_, share_indices)))| {
+                receiver_open(com, vals, ot_payload, indices, share_indices, params)
+            },
+        )
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut bytes = Vec::with_capacity(self.responses_len());
+        for (encoded, _, _) in self.encodings.iter() {
+            bytes.extend(to_bytes(encoded));
+        }
+        return Ok(bytes);
+    }
+
+    pub(crate) fn responses_len(&self) -> usize {
+        return self.encodings.len() * self.params.code_length() * F::BYTES;
+    }
+
+    // decodes y(x) from the senders responses, and picks the seeds of the receivers
+    // challenges
+    pub(crate) fn decode<Crng: CryptoRng + Rng>(
+        &mut self,
+        wss: &[u8],
+        rng: &mut Crng,
+    ) -> Result<Vec<u8>, OleError> {
+        let params = self.params;
+        let wss: Vec<F> = to_elements(wss)?;
+        self.y_polys = par_map(
+            wss.chunks(params.code_length())
+                .zip(&self.encodings)
+                .zip(&self.masks)
+                .collect(),
+            |((ws, (_, _, indices)), mask)| receiver_decode(ws.to_vec(), indices, mask, params),
         );
-        let mut hasher = Sha256::new();
-        secret.into_repr().write_be(&mut hasher)?;
-        let com_check = hasher.finalize();
-        assert_eq!(com_check.as_slice(), com);
+        self.zr_seeds = self.encodings.iter().map(|_| rng.gen::<Block>()).collect();
+        return Ok(self
+            .zr_seeds
+            .iter()
+            .flat_map(|s| s.as_ref().to_vec())
+            .collect());
+    }
 
-        for v in encoded.iter() {
-            channel.write_block(&v.to_block())?;
+    pub(crate) fn openings_len(&self) -> usize {
+        return self.encodings.len() * (2 * self.params.challenges() * F::BYTES + 16);
+    }
+
+    // checks the senders a(z), b(z) at the receivers challenges, and evaluates x(z),
+    // y(z) at the senders challenges
+    pub(crate) fn check(&self, openings: &[u8]) -> Result<Vec<u8>, OleError> {
+        let k = self.params.challenges();
+        let opening_len = 2 * k * F::BYTES + 16;
+        let evaluations = par_map(
+            self.encodings
+                .iter()
+                .zip(&self.y_polys)
+                .zip(&self.zr_seeds)
+                .zip(openings.chunks(opening_len))
+                .collect(),
+            |((((_, x_poly, _), y_poly), zr_seed), opening)| {
+                let (ab_zr, zs_seed) = opening.split_at(2 * k * F::BYTES);
+                let ab_zr: Vec<F> = to_elements(ab_zr)?;
+                let (a_zr, b_zr) = ab_zr.split_at(k);
+                let zr: Vec<F> = expand_field_vec(zr_seed, k);
+                receiver_check(x_poly, y_poly, &zr, a_zr, b_zr)?;
+                let zs: Vec<F> = expand_field_vec(&to_seed(zs_seed), k);
+                Ok((evaluate(x_poly, &zs), evaluate(y_poly, &zs)))
+            },
+        )
+        .into_iter()
+        .collect::<Result<Vec<_>, OleError>>()?;
+
+        let mut bytes = Vec::with_capacity(self.encodings.len() * 2 * k * F::BYTES);
+        for (x_zs, y_zs) in evaluations.iter() {
+            bytes.extend(to_bytes(x_zs));
+            bytes.extend(to_bytes(y_zs));
         }
-        channel.flush()?;
+        return Ok(bytes);
+    }
+
+    // the outputs of each instance
+    pub(crate) fn output(self) -> Vec<Vec<F>> {
+        let params = self.params;
+        return par_map(self.y_polys, |y_poly| receiver_output(y_poly, params));
+    }
+}
+
+// a seed of 16 bytes received from the peer
+fn to_seed(bytes: &[u8]) -> Block {
+    let mut seed = Block::default();
+    seed.as_mut().copy_from_slice(bytes);
+    return seed;
+}
+
+// The steps of a single OLE instance that don't touch the channel, run by the rounds
+// above and shared with vole.
 
-        let w_blocks = channel.read_blocks(F::B)?;
-        let mut ws: Vec<F> = w_blocks.iter().map(|w| (*w).into()).collect();
-        for (i, ti) in indices.iter().zip(&mask) {
-            ws[*i].sub_assign(&ti);
+// the commitment, OT inputs, OT payload and masks of an instance, see sender_commit
+type Commit<F> = (Vec<u8>, Vec<(Block, Block)>, Vec<u8>, Vec<F>);
+
+// Commits to a random secret, and builds the OT messages (share_i, mask_i) from a
+// sharing of the secret and random masks. Returns the commitment, the OT inputs and
+// payload as built by ot_messages, and the masks
+pub(crate) fn sender_commit<F: OleField, Crng: CryptoRng + Rng>(
+    params: &OleParams<F>,
+    rng: &mut Crng,
+) -> Result<Commit<F>, OleError> {
+    let mask: Vec<F> = (0..params.code_length()).map(|_| F::random(rng)).collect();
+    let secret = F::random(rng);
+    let mut shares: Vec<F> =
+        shamir::share(&secret, params.noisy() as u64, params.beta_domain(), rng);
+    shares.truncate(params.code_length());
+
+    let mut hasher = Sha256::new();
+    hasher.update(to_bytes(&[secret]));
+    let com = hasher.finalize();
+
+    let (ot_input, ot_payload) = ot_messages(&shares, &mask, rng);
+    return Ok((com.to_vec(), ot_input, ot_payload, mask));
+}
+
+// Encodes a and b as polynomials, and computes the senders response w = a*v + b + mask
+// to the receivers encoding v. Returns (a_poly, b_poly, w)
+pub(crate) fn sender_response<F: OleField, Crng: CryptoRng + Rng>(
+    a: &[F],
+    b: &[F],
+    v: &[F],
+    mask: &[F],
+    params: &OleParams<F>,
+    rng: &mut Crng,
+) -> (Vec<F>, Vec<F>, Vec<F>) {
+    let mut a_poly = a.to_vec();
+    a_poly.resize_with(params.batch_size(), || F::random(rng));
+    params.input_domain().fft_inverse(&mut a_poly);
+    let mut a_vals = params
+        .beta_domain()
+        .fft_pruned(&a_poly, params.code_length());
+
+    let mut b_poly = encoding::pad_every_other(b, rng);
+    b_poly.resize_with(params.alpha_order(), || F::random(rng));
+    params.alpha_domain().fft_inverse(&mut b_poly);
+    let b_vals = params
+        .beta_domain()
+        .fft_pruned(&b_poly, params.code_length());
+
+    F::batch_mul(&mut a_vals, v);
+    F::batch_add(&mut a_vals, &b_vals[..v.len()]);
+    F::batch_add(&mut a_vals, mask);
+    return (a_poly, b_poly, a_vals);
+}
+
+// checks a(z)*x(z) + b(z) = y(z) for the receivers x(z), y(z) at every challenge z in zs
+pub(crate) fn sender_check<F: OleField>(
+    a_poly: &[F],
+    b_poly: &[F],
+    zs: &[F],
+    x_zs: &[F],
+    y_zs: &[F],
+) -> Result<(), OleError> {
+    let mut ax_b = evaluate(a_poly, zs);
+    F::batch_mul(&mut ax_b, x_zs);
+    F::batch_add(&mut ax_b, &evaluate(b_poly, zs));
+    if ax_b != y_zs {
+        return Err(OleError::SenderCheckFailed);
+    }
+    return Ok(());
+}
+
+// OT choices for the receiver, true at the (sorted) indices of its encoding where it
+// needs the mask, and false where it receives a share. Also returns the share indices
+pub(crate) fn receiver_choices(indices: &[usize], n: usize) -> (Vec<bool>, Vec<usize>) {
+    let mut share_indices = vec![];
+    let mut choices = vec![false; n];
+    let mut j = 0;
+    for (i, choice) in choices.iter_mut().enumerate() {
+        if (j < indices.len()) && (i == indices[j]) {
+            *choice = true;
+            j += 1;
+        } else {
+            share_indices.push(i);
         }
+    }
+    return (choices, share_indices);
+}
 
-        let mut y_poly = encoding::decode_reed_solomon(&mut ws, &indices);
-        assert!(y_poly.len() == F::A);
+// Reconstructs the senders secret from the received shares and checks it against the
+// commitment. Returns the received masks
+pub(crate) fn receiver_open<F: OleField>(
+    com: &[u8],
+    vals: &[Block],
+    ot_payload: &[u8],
+    indices: &[usize],
+    share_indices: &[usize],
+    params: &OleParams<F>,
+) -> Result<Vec<F>, OleError> {
+    let mask = indices
+        .iter()
+        .map(|i| ot_output(vals, ot_payload, *i, true))
+        .collect::<Result<Vec<F>, _>>()?;
+    let shares = share_indices
+        .iter()
+        .map(|i| ot_output(vals, ot_payload, *i, false))
+        .collect::<Result<Vec<F>, _>>()?;
 
-        let zr = F::random(rng);
-        channel.write_block(&zr.to_block())?;
+    let secret = shamir::reconstruct_with(
+        share_indices,
+        &shares,
+        params.noisy() as u64,
+        params.beta_domain(),
+        params.mul_domains(),
+    );
+    let mut hasher = Sha256::new();
+    hasher.update(to_bytes(&[secret]));
+    let com_check = hasher.finalize();
+    if com_check[..] != com[..] {
+        return Err(OleError::CommitmentMismatch);
+    }
+    return Ok(mask);
+}
+
+// Removes the masks from the senders response and decodes y = a*x + b
+pub(crate) fn receiver_decode<F: OleField>(
+    mut ws: Vec<F>,
+    indices: &[usize],
+    mask: &[F],
+    params: &OleParams<F>,
+) -> Vec<F> {
+    for (i, ti) in indices.iter().zip(mask) {
+        ws[*i].sub_assign(ti);
+    }
+
+    // the remainder of the decoding is trimmed of leading zeros, which the sender can
+    // cause by zeroing the top coefficient of b(x)
+    let mut y_poly = encoding::decode_reed_solomon(&ws, indices, params);
+    y_poly.resize(params.alpha_order(), F::zero());
+    return y_poly;
+}
+
+// checks x(z)*a(z) + b(z) = y(z) for the senders a(z), b(z) at every challenge z in zr
+pub(crate) fn receiver_check<F: OleField>(
+    x_poly: &[F],
+    y_poly: &[F],
+    zr: &[F],
+    a_zr: &[F],
+    b_zr: &[F],
+) -> Result<(), OleError> {
+    let mut xa_b = evaluate(x_poly, zr);
+    F::batch_mul(&mut xa_b, a_zr);
+    F::batch_add(&mut xa_b, b_zr);
+    if xa_b != evaluate(y_poly, zr) {
+        return Err(OleError::ReceiverCheckFailed);
+    }
+    return Ok(());
+}
+
+// evaluates poly at each of the points
+pub(crate) fn evaluate<F: OleField>(poly: &[F], points: &[F]) -> Vec<F> {
+    return poly::horner_batch(poly, points);
+}
+
+// OT of field elements, one OT per pair (m0_i, m1_i). Elements that fit a block are
+// sent by the OT itself. Larger elements are encrypted under random keys (k_0, k_1),
+// which are sent by the OT instead, and the sender sends m_0 xor G(k_0) and
+// m_1 xor G(k_1) for the AES based PRG G. Returns the OT inputs, and the encrypted
+// elements to send after the OTs
+pub(crate) fn ot_messages<F: OleField, Crng: CryptoRng + Rng>(
+    m0: &[F],
+    m1: &[F],
+    rng: &mut Crng,
+) -> (Vec<(Block, Block)>, Vec<u8>) {
+    if F::BYTES <= 16 {
+        let ot_input = m0
+            .iter()
+            .zip(m1)
+            .map(|(x, y)| (to_block(x), to_block(y)))
+            .collect();
+        return (ot_input, vec![]);
+    }
+
+    let mut ot_input = Vec::with_capacity(m0.len());
+    let mut ot_payload = vec![0u8; ot_payload_len::<F>(m0.len())];
+    for ((x, y), chunk) in m0.iter().zip(m1).zip(ot_payload.chunks_mut(2 * F::BYTES)) {
+        let (k0, k1) = (rng.gen::<Block>(), rng.gen::<Block>());
+        let (c0, c1) = chunk.split_at_mut(F::BYTES);
+        x.encode(c0);
+        xor_prg(&k0, c0);
+        y.encode(c1);
+        xor_prg(&k1, c1);
+        ot_input.push((k0, k1));
+    }
+    return (ot_input, ot_payload);
+}
+
+// number of bytes the OT sender sends after n OTs of field elements
+pub(crate) fn ot_payload_len<F: OleField>(n: usize) -> usize {
+    if F::BYTES <= 16 {
+        return 0;
+    }
+    return 2 * n * F::BYTES;
+}
+
+// The element received by the i'th OT of ot_messages, given the choice made
+fn ot_output<F: OleField>(
+    vals: &[Block],
+    ot_payload: &[u8],
+    i: usize,
+    choice: bool,
+) -> Result<F, OleError> {
+    if F::BYTES <= 16 {
+        return from_block(&vals[i]);
+    }
+    let offset = (2 * i + choice as usize) * F::BYTES;
+    let mut bytes = ot_payload[offset..offset + F::BYTES].to_vec();
+    xor_prg(&vals[i], &mut bytes);
+    return Ok(F::decode(&bytes)?);
+}
+
+// xors bytes with the output of the AES based PRG on seed key
+fn xor_prg(key: &Block, bytes: &mut [u8]) {
+    let mut pad = vec![0u8; bytes.len()];
+    AesRng::from_seed(*key).fill_bytes(&mut pad);
+    for (b, p) in bytes.iter_mut().zip(pad) {
+        *b ^= p;
+    }
+}
+
+// encodes an element of at most 16 bytes in a block, padded with zeros
+fn to_block<F: OleField>(x: &F) -> Block {
+    let mut bytes = [0u8; 16];
+    x.encode(&mut bytes[..F::BYTES]);
+    return Block::from(bytes);
+}
+
+// strict decoding of to_block
+fn from_block<F: OleField>(block: &Block) -> Result<F, OleError> {
+    let bytes: &[u8] = block.as_ref();
+    if bytes[F::BYTES..].iter().any(|b| *b != 0) {
+        return Err(PrimeFieldDecodingError::NotInField(format!("{:?}", block)).into());
+    }
+    return Ok(F::decode(&bytes[..F::BYTES])?);
+}
+
+// writes field elements in their canonical encoding, without flushing
+pub(crate) fn write_elements<F: OleField, C: AbstractChannel>(
+    channel: &mut C,
+    elements: &[F],
+) -> Result<(), OleError> {
+    channel.write_bytes(&to_bytes(elements))?;
+    return Ok(());
+}
+
+// reads n field elements written by write_elements
+pub(crate) fn read_elements<F: OleField, C: AbstractChannel>(
+    channel: &mut C,
+    n: usize,
+) -> Result<Vec<F>, OleError> {
+    let mut bytes = vec![0u8; n * F::BYTES];
+    channel.read_bytes(&mut bytes)?;
+    return to_elements(&bytes);
+}
+
+// the canonical encodings of the elements, concatenated
+pub(crate) fn to_bytes<F: OleField>(elements: &[F]) -> Vec<u8> {
+    let mut bytes = vec![0u8; elements.len() * F::BYTES];
+    for (chunk, x) in bytes.chunks_mut(F::BYTES).zip(elements) {
+        x.encode(chunk);
+    }
+    return bytes;
+}
+
+// decodes field elements received from the peer
+pub(crate) fn to_elements<F: OleField>(bytes: &[u8]) -> Result<Vec<F>, OleError> {
+    assert_eq!(bytes.len() % F::BYTES, 0);
+    let elements = bytes
+        .chunks(F::BYTES)
+        .map(|chunk| F::decode(chunk))
+        .collect::<Result<Vec<F>, _>>()?;
+    return Ok(elements);
+}
+
+// evaluates y at the even powers of alpha, i.e. the points holding the outputs
+pub(crate) fn receiver_output<F: OleField>(mut y_poly: Vec<F>, params: &OleParams<F>) -> Vec<F> {
+    params.alpha_domain().fft(&mut y_poly);
+    return y_poly.into_iter().step_by(2).collect::<Vec<F>>();
+}
+
+// Pool of random OLEs on the senders side, i.e. random a, b such that the receiver holds
+// y = a*x + b for a random x.
+pub struct RandomOleSender<F: OleField> {
+    a: Vec<F>,
+    b: Vec<F>,
+}
+
+impl<F: OleField> RandomOleSender<F> {
+    pub(crate) fn new(a: Vec<F>, b: Vec<F>) -> Self {
+        Self { a: a, b: b }
+    }
+
+    pub fn len(&self) -> usize {
+        self.a.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.a.is_empty()
+    }
+
+    // online phase: turns the first a.len() random OLEs of the pool into OLEs on the
+    // inputs a, b and removes them from the pool. Fails with RandomOlePoolExhausted,
+    // before touching the channel, if the pool has fewer than a.len() left.
+    // With random OLE (a0, b0), (x0, y0) the receiver sends e = x - x0, and the sender
+    // responds with d = a - a0 and t = b - b0 + a*e, such that y0 + d*x0 + t = a*x + b.
+    pub fn input<C: AbstractChannel>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        channel: &mut C,
+    ) -> Result<(), OleError> {
+        assert_eq!(a.len(), b.len());
+        if a.len() > self.len() {
+            return Err(OleError::RandomOlePoolExhausted);
+        }
+
+        let e: Vec<F> = read_elements(channel, a.len())?;
+        channel.write_bytes(&self.respond(a, b, &e))?;
         channel.flush()?;
 
-        let a_zr: F = (channel.read_block()?).into();
-        let b_zr: F = (channel.read_block()?).into();
-        let zs: F = (channel.read_block()?).into();
+        return Ok(());
+    }
 
-        let mut x_zr = poly::horner(&x_poly, &zr);
-        let y_zr = poly::horner(&y_poly, &zr);
-        x_zr.mul_assign(&a_zr);
-        x_zr.add_assign(&b_zr);
-        assert_eq!(x_zr, y_zr);
+    // takes the first a.len() random OLEs, and returns d followed by t for the
+    // receivers e
+    pub(crate) fn respond(&mut self, a: &[F], b: &[F], e: &[F]) -> Vec<u8> {
+        let a0: Vec<F> = self.a.drain(..a.len()).collect();
+        let b0: Vec<F> = self.b.drain(..b.len()).collect();
 
-        let x_zs = poly::horner(&x_poly, &zs);
-        let y_zs = poly::horner(&y_poly, &zs);
-        channel.write_block(&x_zs.to_block())?;
-        channel.write_block(&y_zs.to_block())?;
+        let d: Vec<F> = a
+            .iter()
+            .zip(&a0)
+            .map(|(ai, a0i)| {
+                let mut d = *ai;
+                d.sub_assign(a0i);
+                d
+            })
+            .collect();
+        let t: Vec<F> = (0..e.len())
+            .map(|i| {
+                let mut t = a[i];
+                t.mul_assign(&e[i]);
+                t.add_assign(&b[i]);
+                t.sub_assign(&b0[i]);
+                t
+            })
+            .collect();
+        let mut bytes = to_bytes(&d);
+        bytes.extend(to_bytes(&t));
+        return bytes;
+    }
+}
+
+// Pool of random OLEs on the receivers side, i.e. random x and y = a*x + b for random
+// a, b held by the sender.
+pub struct RandomOleReceiver<F: OleField> {
+    x: Vec<F>,
+    y: Vec<F>,
+}
+
+impl<F: OleField> RandomOleReceiver<F> {
+    pub(crate) fn new(x: Vec<F>, y: Vec<F>) -> Self {
+        Self { x: x, y: y }
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    // online phase: turns the first x.len() random OLEs of the pool into OLEs on the
+    // input x and removes them from the pool, or fails with RandomOlePoolExhausted. See
+    // RandomOleSender::input.
+    pub fn input<C: AbstractChannel>(
+        &mut self,
+        x: &[F],
+        channel: &mut C,
+    ) -> Result<Vec<F>, OleError> {
+        if x.len() > self.len() {
+            return Err(OleError::RandomOlePoolExhausted);
+        }
+
+        let (x0, y0, e) = self.blind(x);
+        write_elements(channel, &e)?;
         channel.flush()?;
 
-        F::fft2(&mut y_poly, &F::alpha());
-        let result = y_poly.into_iter().step_by(2).collect::<Vec<F>>();
-        // let result: Vec<F> = (0..F::A/2).map(|i| poly::horner(&y_poly, &F::alpha().pow([(2*i) as u64]))).collect();
-        return Ok(result);
+        let d: Vec<F> = read_elements(channel, x.len())?;
+        let t: Vec<F> = read_elements(channel, x.len())?;
+        return Ok(Self::unblind(&x0, &y0, &d, &t));
+    }
+
+    // takes the first x.len() random OLEs (x0, y0), and returns them with e = x - x0
+    pub(crate) fn blind(&mut self, x: &[F]) -> (Vec<F>, Vec<F>, Vec<F>) {
+        let x0: Vec<F> = self.x.drain(..x.len()).collect();
+        let y0: Vec<F> = self.y.drain(..x.len()).collect();
+
+        let e: Vec<F> = x
+            .iter()
+            .zip(&x0)
+            .map(|(xi, x0i)| {
+                let mut e = *xi;
+                e.sub_assign(x0i);
+                e
+            })
+            .collect();
+        return (x0, y0, e);
+    }
+
+    // y = y0 + d*x0 + t for the senders response d, t
+    pub(crate) fn unblind(x0: &[F], y0: &[F], d: &[F], t: &[F]) -> Vec<F> {
+        return (0..x0.len())
+            .map(|i| {
+                let mut y = d[i];
+                y.mul_assign(&x0[i]);
+                y.add_assign(&y0[i]);
+                y.add_assign(&t[i]);
+                y
+            })
+            .collect();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::field::Fp;
+    use crate::extension::tests::Fq2;
+    use crate::field::{BatchField, Fp, OleField};
+    use crate::field255::Fp255;
+    use crate::field64::Fp64;
+    use crate::test_utils::{run_parties, to_channel};
     use ff::Field;
     use rand;
-    use scuttlebutt::channel::Channel;
-    use std::{
-        io::{BufReader, BufWriter},
-        os::unix::net::UnixStream,
-    };
+    use std::os::unix::net::UnixStream;
 
     #[test]
     fn test_ole() {
+        check_ole::<Fp>();
+        check_ole::<Fp64>();
+        check_ole::<Fp255>();
+        check_ole::<Fq2>();
+    }
+
+    fn check_ole<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let (sender, receiver) = UnixStream::pair().unwrap();
-        let b: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
-        let a: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
-        // let a: Vec<Fp> = (0..Fp::A/2).map(|i| Fp::one()).collect();
-        // let b: Vec<Fp> = (0..Fp::A/2).map(|i| Fp::one()).collect();
+        let b: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
+        let a: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
+        // let a: Vec<F> = (0..F::A/2).map(|i| F::one()).collect();
+        // let b: Vec<F> = (0..F::A/2).map(|i| F::one()).collect();
 
         let a_copy = a.to_vec();
         let b_copy = b.to_vec();
-        let handle = std::thread::spawn(move || {
-            let mut rng = rand::thread_rng();
-            let reader = BufReader::new(sender.try_clone().unwrap());
-            let writer = BufWriter::new(sender);
-            let mut channel = Channel::new(reader, writer);
-
-            let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
-            olesender
-                .input(&a_copy, &b_copy, &mut channel, &mut rng)
-                .unwrap();
-        });
-
-        let reader = BufReader::new(receiver.try_clone().unwrap());
-        let writer = BufWriter::new(receiver);
-        let mut channel = Channel::new(reader, writer);
-        let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
-        let x: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
-        let result = olereceiver.input(&x, &mut channel, &mut rng).unwrap();
-        handle.join().unwrap();
-
-        for i in 0..Fp::A / 2 {
+        let x: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
+        let x_copy = x.to_vec();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                olesender
+                    .input(&a_copy, &b_copy, &mut channel, &mut rng)
+                    .unwrap();
+            },
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver.input(&x_copy, &mut channel, &mut rng).unwrap()
+            },
+        );
+
+        for i in 0..F::A / 2 {
             let mut expected = x[i];
             expected.mul_assign(&a[i]);
             expected.add_assign(&b[i]);
@@ -300,67 +1306,528 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ole_arbitrary_length() {
+        check_ole_arbitrary_length::<Fp>();
+        check_ole_arbitrary_length::<Fp64>();
+    }
+
+    fn check_ole_arbitrary_length<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let lengths = [1, F::A / 2, 300, 0];
+        let inputs: Vec<(Vec<F>, Vec<F>)> = lengths
+            .iter()
+            .map(|n| {
+                let a = (0..*n).map(|_| F::random(&mut rng)).collect();
+                let b = (0..*n).map(|_| F::random(&mut rng)).collect();
+                (a, b)
+            })
+            .collect();
+
+        let inputs_copy = inputs.clone();
+        run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                for (a, b) in inputs_copy.iter() {
+                    olesender.input(a, b, &mut channel, &mut rng).unwrap();
+                }
+            },
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                for (a, b) in inputs.iter() {
+                    let x: Vec<F> = (0..a.len()).map(|_| F::random(&mut rng)).collect();
+                    let result = olereceiver.input(&x, &mut channel, &mut rng).unwrap();
+                    assert_eq!(result.len(), x.len());
+                    for i in 0..x.len() {
+                        let mut expected = x[i];
+                        expected.mul_assign(&a[i]);
+                        expected.add_assign(&b[i]);
+                        assert_eq!(result[i], expected);
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_ole_params() {
+        check_ole_params::<Fp>();
+        check_ole_params::<Fp64>();
+    }
+
+    fn check_ole_params<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let params = OleParams::<F>::new(64, 729, 300)
+            .unwrap()
+            .with_challenges(3)
+            .unwrap();
+        // more than one round of instances
+        let n = ROUND_INSTANCES * params.batch_size() + 100;
+        let a: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let x: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let params_copy = params.clone();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                olesender
+                    .input_with_params(&a_copy, &b_copy, &params_copy, &mut channel, &mut rng)
+                    .unwrap();
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver
+                    .input_with_params(&x, &params, &mut channel, &mut rng)
+                    .unwrap()
+            },
+        );
+
+        assert_eq!(result.len(), n);
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_random_ole() {
+        check_random_ole::<Fp>();
+        check_random_ole::<Fp64>();
+        check_random_ole::<Fp255>();
+    }
+
+    fn check_random_ole<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let n = 200;
+        let a: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let x: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                let mut pool = olesender
+                    .input_random::<F, _, _>(n + 50, &mut channel, &mut rng)
+                    .unwrap();
+                pool.input(&a_copy[..100], &b_copy[..100], &mut channel)
+                    .unwrap();
+                pool.input(&a_copy[100..], &b_copy[100..], &mut channel)
+                    .unwrap();
+                assert_eq!(pool.len(), 50);
+                match pool.input(&a_copy[..51], &b_copy[..51], &mut channel) {
+                    Err(OleError::RandomOlePoolExhausted) => (),
+                    r => panic!("expected RandomOlePoolExhausted, got {:?}", r),
+                }
+                assert_eq!(pool.len(), 50);
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                let mut pool = olereceiver
+                    .input_random::<F, _, _>(n + 50, &mut channel, &mut rng)
+                    .unwrap();
+                let mut result = pool.input(&x[..100], &mut channel).unwrap();
+                result.append(&mut pool.input(&x[100..], &mut channel).unwrap());
+                assert_eq!(pool.len(), 50);
+                match pool.input(&x[..51], &mut channel) {
+                    Err(OleError::RandomOlePoolExhausted) => (),
+                    r => panic!("expected RandomOlePoolExhausted, got {:?}", r),
+                }
+                assert_eq!(pool.len(), 50);
+                result
+            },
+        );
+
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_ole_commitment_mismatch() {
+        let (_, result) = run_parties(
+            // a cheating sender that commits to garbage and sends random OT messages
+            |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut ot = KosSender::init(&mut channel, &mut rng).unwrap();
+                let com: [u8; 32] = rng.gen();
+                channel.write_bytes(&com).unwrap();
+                channel.flush().unwrap();
+                let ms: Vec<(Block, Block)> = (0..Fp::B)
+                    .map(|_| {
+                        (
+                            to_block(&Fp::random(&mut rng)),
+                            to_block(&Fp::random(&mut rng)),
+                        )
+                    })
+                    .collect();
+                ot.send(&mut channel, &ms, &mut rng).unwrap();
+            },
+            |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                let x: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
+                olereceiver.input(&x, &mut channel, &mut rng)
+            },
+        );
+
+        match result {
+            Err(OleError::CommitmentMismatch) => (),
+            _ => panic!("expected commitment mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_ole_trimmed_decoding() {
+        let mut rng = rand::thread_rng();
+        let params = OleParams::<Fp>::default();
+        let n = params.batch_size();
+        let a: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let x: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+
+        // a sender that zeroes the top coefficient of b(x), such that the receivers
+        // decoding has degree below alpha_order. Returns the b it actually used
+        let a_copy = a.to_vec();
+        let (b, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                let params = OleParams::<Fp>::default();
+                let k = params.challenges();
+
+                let (com, ot_input, ot_payload, mask) = sender_commit(&params, &mut rng).unwrap();
+                channel.write_bytes(&com).unwrap();
+                channel.flush().unwrap();
+                olesender
+                    .ot_send(&mut channel, &ot_input, &mut rng)
+                    .unwrap();
+                channel.write_bytes(&ot_payload).unwrap();
+                channel.flush().unwrap();
+
+                let v: Vec<Fp> = read_elements(&mut channel, params.code_length()).unwrap();
+                let (a_poly, mut b_poly, _) =
+                    sender_response(&a_copy, &b, &v, &mask, &params, &mut rng);
+                b_poly[params.alpha_order() - 1] = Fp::zero();
+                let mut ws = params
+                    .beta_domain()
+                    .fft_pruned(&a_poly, params.code_length());
+                Fp::batch_mul(&mut ws, &v);
+                Fp::batch_add(
+                    &mut ws,
+                    &params
+                        .beta_domain()
+                        .fft_pruned(&b_poly, params.code_length()),
+                );
+                Fp::batch_add(&mut ws, &mask);
+                write_elements(&mut channel, &ws).unwrap();
+                channel.flush().unwrap();
+
+                let zr: Vec<Fp> = expand_field_vec(&channel.read_block().unwrap(), k);
+                let zs_seed = rng.gen::<Block>();
+                write_elements(&mut channel, &evaluate(&a_poly, &zr)).unwrap();
+                write_elements(&mut channel, &evaluate(&b_poly, &zr)).unwrap();
+                channel.write_block(&zs_seed).unwrap();
+                channel.flush().unwrap();
+                let x_zs: Vec<Fp> = read_elements(&mut channel, k).unwrap();
+                let y_zs: Vec<Fp> = read_elements(&mut channel, k).unwrap();
+                let zs: Vec<Fp> = expand_field_vec(&zs_seed, k);
+                sender_check(&a_poly, &b_poly, &zs, &x_zs, &y_zs).unwrap();
+
+                params.alpha_domain().fft(&mut b_poly);
+                b_poly.into_iter().step_by(2).collect::<Vec<Fp>>()
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver.input(&x, &mut channel, &mut rng).unwrap()
+            },
+        );
+
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_ole_save_restore() {
+        let mut rng = rand::thread_rng();
+        // restored parties reconnect on a new channel, as buffered readers of the old
+        // one may have read ahead
+        let (sender2, receiver2) = UnixStream::pair().unwrap();
+        let session_id = rng.gen::<Block>();
+        let n = Fp::A / 2 + 10;
+        let a: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let x: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let (_, (result, refilled)) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                olesender
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                let mut state = olesender.save_pool(&session_id);
+
+                // reconnect
+                let mut channel = to_channel(sender2);
+                let mut olesender = OleSender::restore_pool(&mut state, &mut channel).unwrap();
+                olesender
+                    .input(&a_copy, &b_copy, &mut channel, &mut rng)
+                    .unwrap();
+                match olesender.input(&a_copy, &b_copy, &mut channel, &mut rng) {
+                    Err(OleError::OTPoolExhausted) => (),
+                    r => panic!("expected OTPoolExhausted, got {:?}", r),
+                }
+
+                // refilling runs the base OTs again
+                olesender
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                olesender
+                    .input(&a_copy, &b_copy, &mut channel, &mut rng)
+                    .unwrap();
+                assert_eq!(olesender.pool.len(), 0);
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                let mut state = olereceiver.save_pool(&session_id);
+
+                let mut channel = to_channel(receiver2);
+                let mut olereceiver = OleReceiver::restore_pool(&mut state, &mut channel).unwrap();
+                let result = olereceiver.input(&x, &mut channel, &mut rng).unwrap();
+                olereceiver
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                let refilled = olereceiver.input(&x, &mut channel, &mut rng).unwrap();
+                (result, refilled)
+            },
+        );
+
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+            assert_eq!(refilled[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_ole_partial_pool() {
+        let mut rng = rand::thread_rng();
+        // a pool for one instance of small parameters covers only part of the OTs of an
+        // instance with the default ones, the rest come from OT-extension
+        let small = OleParams::<Fp>::new(8, 27, 19).unwrap();
+        let n = Fp::A / 2;
+        let a: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let b: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+        let x: Vec<Fp> = (0..n).map(|_| Fp::random(&mut rng)).collect();
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let small_copy = small.clone();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                olesender
+                    .precompute_with_params(1, &small_copy, &mut channel, &mut rng)
+                    .unwrap();
+                assert_eq!(olesender.pool.len(), small_copy.code_length());
+                olesender
+                    .input(&a_copy, &b_copy, &mut channel, &mut rng)
+                    .unwrap();
+                assert_eq!(olesender.pool.len(), 0);
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver
+                    .precompute_with_params(1, &small, &mut channel, &mut rng)
+                    .unwrap();
+                let result = olereceiver.input(&x, &mut channel, &mut rng).unwrap();
+                assert_eq!(olereceiver.pool.len(), 0);
+                result
+            },
+        );
+
+        for i in 0..n {
+            let mut expected = x[i];
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_ole_restore_stale_state() {
+        let mut rng = rand::thread_rng();
+        let session_id = rng.gen::<Block>();
+        let n = Fp::A;
+
+        run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut olesender = OleSender::init(&mut channel, &mut rng).unwrap();
+                olesender
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                let mut stale_state = olesender.save_pool(&session_id);
+                let stale_copy = stale_state.clone();
+
+                // the receiver has used some of the OTs since this state was saved
+                let mut olesender =
+                    OleSender::restore_pool(&mut stale_state, &mut channel).unwrap();
+                let a: Vec<Fp> = (0..10).map(|_| Fp::random(&mut rng)).collect();
+                olesender.input(&a, &a, &mut channel, &mut rng).unwrap();
+                let mut state = olesender.save_pool(&session_id);
+
+                // restoring the same bytes again fails without the receiver
+                assert!(matches!(
+                    OleSender::restore_pool(&mut stale_state, &mut channel),
+                    Err(OleError::StateReplayed)
+                ));
+                assert!(matches!(
+                    OleSender::restore_pool(&mut stale_copy.clone(), &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+
+                // a stale generation is rejected even with as many OTs left
+                let state_copy = state.clone();
+                let olesender = OleSender::restore_pool(&mut state, &mut channel).unwrap();
+                let mut state = olesender.save_pool(&session_id);
+                assert!(matches!(
+                    OleSender::restore_pool(&mut state_copy.clone(), &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+
+                let olesender = OleSender::restore_pool(&mut state, &mut channel).unwrap();
+                let mut state = olesender.save_pool(&session_id);
+                assert!(matches!(
+                    OleSender::restore_pool(&mut state, &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+            },
+            |mut channel| {
+                let mut olereceiver = OleReceiver::init(&mut channel, &mut rng).unwrap();
+                olereceiver
+                    .precompute::<Fp, _, _>(n, &mut channel, &mut rng)
+                    .unwrap();
+                let mut state = olereceiver.save_pool(&session_id);
+
+                let mut olereceiver = OleReceiver::restore_pool(&mut state, &mut channel).unwrap();
+                let x: Vec<Fp> = (0..10).map(|_| Fp::random(&mut rng)).collect();
+                olereceiver.input(&x, &mut channel, &mut rng).unwrap();
+                let mut state = olereceiver.save_pool(&session_id);
+
+                // the replayed state of the sender is rejected
+                assert!(matches!(
+                    OleReceiver::restore_pool(&mut state, &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+
+                let olereceiver = OleReceiver::restore_pool(&mut state, &mut channel).unwrap();
+                let mut state = olereceiver.save_pool(&session_id);
+                assert!(matches!(
+                    OleReceiver::restore_pool(&mut state, &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+
+                // state of another session is rejected
+                let olereceiver = OleReceiver::restore_pool(&mut state, &mut channel).unwrap();
+                let mut other_state = olereceiver.save_pool(&rng.gen::<Block>());
+                assert!(matches!(
+                    OleReceiver::restore_pool(&mut other_state, &mut channel),
+                    Err(OleError::SessionMismatch)
+                ));
+            },
+        );
+    }
+
     #[test]
     fn test_channel() {
+        check_channel::<Fp>();
+        check_channel::<Fp255>();
+    }
+
+    fn check_channel<F: OleField>() {
         let n = 1000u64;
-        let (sender, receiver) = UnixStream::pair().unwrap();
-        let xs: Vec<Fp> = (0..n).map(|i| Fp::alpha().pow([i])).collect();
-
-        let handle = std::thread::spawn(move || {
-            let xs: Vec<Fp> = (0..n).map(|i| Fp::alpha().pow([i])).collect();
-            let reader = BufReader::new(receiver.try_clone().unwrap());
-            let writer = BufWriter::new(receiver);
-            let mut channel = Channel::new(reader, writer);
-            for x in xs {
-                let x_received: Fp = (channel.read_block().unwrap()).into();
-                assert_eq!(x, x_received);
-            }
-        });
+        let xs: Vec<F> = (0..n).map(|i| F::alpha().pow([i])).collect();
 
-        let reader = BufReader::new(sender.try_clone().unwrap());
-        let writer = BufWriter::new(sender);
-        let mut channel = Channel::new(reader, writer);
-        for x in xs.iter() {
-            channel.write_block(&x.to_block()).unwrap();
-        }
-        channel.flush().unwrap();
-        handle.join().unwrap();
+        run_parties(
+            move |mut channel| {
+                let xs: Vec<F> = (0..n).map(|i| F::alpha().pow([i])).collect();
+                for x in xs {
+                    let x_received: Vec<F> = read_elements(&mut channel, 1).unwrap();
+                    assert_eq!(x, x_received[0]);
+                }
+            },
+            |mut channel| {
+                for x in xs.iter() {
+                    write_elements(&mut channel, &[*x]).unwrap();
+                }
+                channel.flush().unwrap();
+            },
+        );
     }
 
     #[test]
     // test copied from ocelot's test suite
     fn test_ot() {
+        check_ot::<Fp>();
+        check_ot::<Fp255>();
+    }
+
+    fn check_ot<F: OleField>() {
         let mut rng = rand::thread_rng();
 
         let ninputs = 128;
-        let m0s: Vec<Fp> = (0..ninputs).map(|_| Fp::random(&mut rng)).collect();
-        let m1s: Vec<Fp> = (0..ninputs).map(|_| Fp::random(&mut rng)).collect();
+        let m0s: Vec<F> = (0..ninputs).map(|_| F::random(&mut rng)).collect();
+        let m1s: Vec<F> = (0..ninputs).map(|_| F::random(&mut rng)).collect();
         let bs: Vec<bool> = (0..ninputs).map(|_| rand::random::<bool>()).collect();
         let m0s_ = m0s.clone();
         let m1s_ = m1s.clone();
-        let (sender, receiver) = UnixStream::pair().unwrap();
-
-        let handle = std::thread::spawn(move || {
-            let mut rng = rand::thread_rng();
-            let reader = BufReader::new(sender.try_clone().unwrap());
-            let writer = BufWriter::new(sender);
-            let mut channel = Channel::new(reader, writer);
-            let mut ot = KosSender::init(&mut channel, &mut rng).unwrap();
-            let ms = m0s
-                .into_iter()
-                .zip(m1s.into_iter())
-                .map(|(m0, m1)| (m0.to_block(), m1.to_block()))
-                .collect::<Vec<(Block, Block)>>();
-            ot.send(&mut channel, &ms, &mut rng).unwrap();
-        });
-
-        let reader = BufReader::new(receiver.try_clone().unwrap());
-        let writer = BufWriter::new(receiver);
-        let mut channel = Channel::new(reader, writer);
-        let mut ot = KosReceiver::init(&mut channel, &mut rng).unwrap();
-        let result_ = ot.receive(&mut channel, &bs, &mut rng).unwrap();
-        let result: Vec<Fp> = result_.iter().map(|v| (*v).into()).collect();
-        handle.join().unwrap();
+
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut ot = KosSender::init(&mut channel, &mut rng).unwrap();
+                let (ms, payload) = ot_messages(&m0s, &m1s, &mut rng);
+                ot.send(&mut channel, &ms, &mut rng).unwrap();
+                channel.write_bytes(&payload).unwrap();
+                channel.flush().unwrap();
+            },
+            |mut channel| {
+                let mut ot = KosReceiver::init(&mut channel, &mut rng).unwrap();
+                let result_ = ot.receive(&mut channel, &bs, &mut rng).unwrap();
+                let mut payload = vec![0u8; ot_payload_len::<F>(ninputs)];
+                channel.read_bytes(&mut payload).unwrap();
+                (0..ninputs)
+                    .map(|j| ot_output(&result_, &payload, j, bs[j]).unwrap())
+                    .collect::<Vec<F>>()
+            },
+        );
         for j in 0..ninputs {
             assert_eq!(result[j], if bs[j] { m1s_[j] } else { m0s_[j] });
         }
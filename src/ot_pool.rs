@@ -0,0 +1,251 @@
+use crate::error::OleError;
+use scuttlebutt::{channel::AbstractChannel, Block};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::io;
+
+// Pools of precomputed random OTs, which can be saved and restored.
+//
+// The state of ocelot's OT-extension is private, so it can't be persisted itself.
+// Instead the parties run OT-extension ahead of time on random inputs, and store the
+// results. A random OT is turned into a chosen-message OT with Beaver's
+// derandomization: the receiver sends d = b xor c for its choice b and random choice c,
+// and the sender sends (m_0 xor r_d, m_1 xor r_(1-d)).
+//
+// Saved state is bound to a session id and carries a generation, which every save of a
+// pool bumps. On restore the parties check that they agree on the session id, the
+// generation and the number of remaining OTs, which rejects state that is stale on one
+// side, even once precompute brought the pool back to the same size. Restoring also
+// overwrites the magic of the saved bytes, so that restoring the same bytes again fails
+// with StateReplayed. Both parties restoring copies of the same old state still reuses
+// OTs, which only the caller can prevent, by storing the invalidated bytes before using
+// the restored pool.
+
+const SENDER_MAGIC: &[u8; 8] = b"OLEOTS01";
+const RECEIVER_MAGIC: &[u8; 8] = b"OLEOTR01";
+// replaces the magic of restored state
+const RESTORED_MAGIC: &[u8; 8] = b"OLEOTX01";
+
+// random OT pairs (r_0, r_1) of the OT sender
+#[derive(Default)]
+pub(crate) struct SenderPool {
+    ots: VecDeque<(Block, Block)>,
+    generation: u64,
+}
+
+// random choices c and messages r_c of the OT receiver
+#[derive(Default)]
+pub(crate) struct ReceiverPool {
+    ots: VecDeque<(bool, Block)>,
+    generation: u64,
+}
+
+impl SenderPool {
+    pub(crate) fn len(&self) -> usize {
+        self.ots.len()
+    }
+
+    pub(crate) fn extend(&mut self, ots: Vec<(Block, Block)>) {
+        self.ots.extend(ots);
+    }
+
+    // chosen-message OT send of inputs, consuming inputs.len() OTs from the pool
+    pub(crate) fn send<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+    ) -> Result<(), OleError> {
+        assert!(inputs.len() <= self.ots.len());
+        let mut d = vec![0u8; inputs.len()];
+        channel.read_bytes(&mut d)?;
+        for ((m0, m1), di) in inputs.iter().zip(d) {
+            let (r0, r1) = self.ots.pop_front().unwrap();
+            let (rd, rnd) = if di == 1 { (r1, r0) } else { (r0, r1) };
+            channel.write_block(&(*m0 ^ rd))?;
+            channel.write_block(&(*m1 ^ rnd))?;
+        }
+        channel.flush()?;
+        return Ok(());
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // moves the OTs into the saved state, as the next generation
+    pub(crate) fn save(&mut self, session_id: &Block) -> Vec<u8> {
+        self.generation += 1;
+        let mut bytes = state_header(SENDER_MAGIC, session_id, self.generation, self.len());
+        for (r0, r1) in self.ots.drain(..) {
+            bytes.extend_from_slice(r0.as_ref());
+            bytes.extend_from_slice(r1.as_ref());
+        }
+        return bytes;
+    }
+
+    pub(crate) fn restore(bytes: &[u8]) -> Result<(Block, Self), OleError> {
+        let (session_id, generation, n, mut rest) = parse_header(SENDER_MAGIC, bytes, 32)?;
+        let mut ots = VecDeque::with_capacity(n);
+        for _ in 0..n {
+            let r0 = read_block(&mut rest);
+            let r1 = read_block(&mut rest);
+            ots.push_back((r0, r1));
+        }
+        let pool = Self {
+            ots: ots,
+            generation: generation,
+        };
+        return Ok((session_id, pool));
+    }
+}
+
+impl ReceiverPool {
+    pub(crate) fn len(&self) -> usize {
+        self.ots.len()
+    }
+
+    pub(crate) fn extend(&mut self, choices: Vec<bool>, ots: Vec<Block>) {
+        self.ots.extend(choices.into_iter().zip(ots));
+    }
+
+    // chosen-message OT receive with choices, consuming choices.len() OTs from the pool
+    pub(crate) fn receive<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        choices: &[bool],
+    ) -> Result<Vec<Block>, OleError> {
+        assert!(choices.len() <= self.ots.len());
+        let ots: Vec<(bool, Block)> = self.ots.drain(..choices.len()).collect();
+        let d: Vec<u8> = choices
+            .iter()
+            .zip(&ots)
+            .map(|(b, (c, _))| (b ^ c) as u8)
+            .collect();
+        channel.write_bytes(&d)?;
+        channel.flush()?;
+
+        let mut result = Vec::with_capacity(choices.len());
+        for (b, (_, rc)) in choices.iter().zip(ots) {
+            let e0 = channel.read_block()?;
+            let e1 = channel.read_block()?;
+            let eb = if *b { e1 } else { e0 };
+            result.push(eb ^ rc);
+        }
+        return Ok(result);
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // moves the OTs into the saved state, as the next generation
+    pub(crate) fn save(&mut self, session_id: &Block) -> Vec<u8> {
+        self.generation += 1;
+        let mut bytes = state_header(RECEIVER_MAGIC, session_id, self.generation, self.len());
+        for (c, rc) in self.ots.drain(..) {
+            bytes.push(c as u8);
+            bytes.extend_from_slice(rc.as_ref());
+        }
+        return bytes;
+    }
+
+    pub(crate) fn restore(bytes: &[u8]) -> Result<(Block, Self), OleError> {
+        let (session_id, generation, n, mut rest) = parse_header(RECEIVER_MAGIC, bytes, 17)?;
+        let mut ots = VecDeque::with_capacity(n);
+        for _ in 0..n {
+            let c = match rest[0] {
+                0 => false,
+                1 => true,
+                _ => return Err(invalid_state()),
+            };
+            rest = &rest[1..];
+            ots.push_back((c, read_block(&mut rest)));
+        }
+        let pool = Self {
+            ots: ots,
+            generation: generation,
+        };
+        return Ok((session_id, pool));
+    }
+}
+
+// Checks that both parties restored state of the same session and generation, with
+// the same number of remaining OTs
+pub(crate) fn check_session<C: AbstractChannel>(
+    channel: &mut C,
+    session_id: &Block,
+    generation: u64,
+    n: usize,
+) -> Result<(), OleError> {
+    channel.write_block(session_id)?;
+    channel.write_bytes(&generation.to_le_bytes())?;
+    channel.write_bytes(&(n as u64).to_le_bytes())?;
+    channel.flush()?;
+
+    let other_id = channel.read_block()?;
+    let mut other_generation = [0u8; 8];
+    channel.read_bytes(&mut other_generation)?;
+    let mut other_n = [0u8; 8];
+    channel.read_bytes(&mut other_n)?;
+    if other_id != *session_id
+        || u64::from_le_bytes(other_generation) != generation
+        || u64::from_le_bytes(other_n) != n as u64
+    {
+        return Err(OleError::SessionMismatch);
+    }
+    return Ok(());
+}
+
+// Marks saved state as restored, see restored
+pub(crate) fn invalidate(bytes: &mut [u8]) {
+    bytes[..8].copy_from_slice(RESTORED_MAGIC);
+}
+
+// whether the bytes are saved state that was restored before
+pub(crate) fn restored(bytes: &[u8]) -> bool {
+    return bytes.len() >= 8 && &bytes[..8] == RESTORED_MAGIC;
+}
+
+// magic | session id | generation | number of OTs, the numbers as u64 little-endian
+fn state_header(magic: &[u8; 8], session_id: &Block, generation: u64, n: usize) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.extend_from_slice(session_id.as_ref());
+    bytes.extend_from_slice(&generation.to_le_bytes());
+    bytes.extend_from_slice(&(n as u64).to_le_bytes());
+    return bytes;
+}
+
+// Returns the session id, the generation, the number of OTs and the encoded OTs, each
+// entry_len bytes
+fn parse_header<'a>(
+    magic: &[u8; 8],
+    bytes: &'a [u8],
+    entry_len: usize,
+) -> Result<(Block, u64, usize, &'a [u8]), OleError> {
+    if restored(bytes) {
+        return Err(OleError::StateReplayed);
+    }
+    if bytes.len() < 40 || &bytes[..8] != magic {
+        return Err(invalid_state());
+    }
+    let mut rest = &bytes[8..];
+    let session_id = read_block(&mut rest);
+    let generation = u64::from_le_bytes(rest[..8].try_into().unwrap());
+    let n = u64::from_le_bytes(rest[8..16].try_into().unwrap()) as usize;
+    let rest = &rest[16..];
+    if n.checked_mul(entry_len) != Some(rest.len()) {
+        return Err(invalid_state());
+    }
+    return Ok((session_id, generation, n, rest));
+}
+
+fn read_block(bytes: &mut &[u8]) -> Block {
+    let mut block = Block::default();
+    block.as_mut().copy_from_slice(&bytes[..16]);
+    *bytes = &bytes[16..];
+    return block;
+}
+
+fn invalid_state() -> OleError {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid saved OT state").into()
+}
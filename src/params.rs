@@ -0,0 +1,315 @@
+use crate::error::OleError;
+use crate::fft::EvaluationDomain;
+use crate::field::OleField;
+use crate::poly::MulDomains;
+
+// Parameters of an OLE instance.
+//
+// The receiver's input is interpolated on the even powers of alpha and encoded at the
+// first alpha_order + noisy powers of beta, of which noisy positions are replaced by
+// random values. Every instance gives alpha_order / 2 OLEs, and costs
+// alpha_order + noisy OTs and field elements in each direction.
+//
+// At the end of an instance both parties check the polynomial identity
+// x*a + b = y, of degree < alpha_order, at challenges random points each. Each point
+// catches a cheating party except with probability alpha_order / |F|.
+#[derive(Clone, Debug)]
+pub struct OleParams<F: OleField> {
+    alpha_domain: EvaluationDomain<F>, // powers of alpha
+    input_domain: EvaluationDomain<F>, // even powers of alpha, holding the inputs
+    beta_domain: EvaluationDomain<F>,  // powers of beta
+    noisy: usize,
+    challenges: usize,
+    mul_domains: MulDomains<F>, // for the polynomial divisions of the decodings
+}
+
+impl<F: OleField> OleParams<F> {
+    // alpha_order must be a power of 2 and beta_order a power of 3, both dividing |F|-1,
+    // and the code length alpha_order + noisy can't exceed beta_order
+    pub fn new(alpha_order: usize, beta_order: usize, noisy: usize) -> Result<Self, OleError> {
+        if alpha_order < 2 || !alpha_order.is_power_of_two() {
+            return Err(OleError::InvalidParams(
+                "alpha order must be a power of 2 of at least 2",
+            ));
+        }
+        if !is_power_of_three(beta_order) {
+            return Err(OleError::InvalidParams(
+                "beta order must be a power of 3 of at least 3",
+            ));
+        }
+        if noisy == 0 {
+            return Err(OleError::InvalidParams(
+                "the encoding needs at least one noisy position",
+            ));
+        }
+        if alpha_order + noisy > beta_order {
+            return Err(OleError::InvalidParams(
+                "alpha order plus noisy positions exceeds the beta order",
+            ));
+        }
+        let alpha = F::subgroup_generator(alpha_order as u64)
+            .ok_or(OleError::InvalidParams("alpha order does not divide |F|-1"))?;
+        let beta = F::subgroup_generator(beta_order as u64)
+            .ok_or(OleError::InvalidParams("beta order does not divide |F|-1"))?;
+        Ok(Self::from_generators(
+            alpha_order,
+            alpha,
+            beta_order,
+            beta,
+            noisy,
+        ))
+    }
+
+    fn from_generators(
+        alpha_order: usize,
+        alpha: F,
+        beta_order: usize,
+        beta: F,
+        noisy: usize,
+    ) -> Self {
+        assert!(F::is_valid_field(), "the field definition is invalid");
+        let mut alpha2 = alpha;
+        alpha2.square();
+        Self {
+            alpha_domain: EvaluationDomain::new(alpha_order, alpha),
+            input_domain: EvaluationDomain::new(alpha_order / 2, alpha2),
+            beta_domain: EvaluationDomain::new(beta_order, beta),
+            noisy: noisy,
+            challenges: 1,
+            mul_domains: MulDomains::new(),
+        }
+    }
+
+    // sets the number of challenge points of each consistency check
+    pub fn with_challenges(mut self, challenges: usize) -> Result<Self, OleError> {
+        if challenges == 0 {
+            return Err(OleError::InvalidParams(
+                "the consistency checks need at least one challenge",
+            ));
+        }
+        self.challenges = challenges;
+        Ok(self)
+    }
+
+    // sets the least number of challenge points for a soundness error of at most
+    // 2^-bits
+    pub fn with_statistical_security(self, bits: u32) -> Result<Self, OleError> {
+        let per_challenge = self.bits_per_challenge();
+        if per_challenge == 0 {
+            return Err(OleError::InvalidParams(
+                "the field is too small for the consistency checks",
+            ));
+        }
+        let challenges = bits.div_ceil(per_challenge);
+        self.with_challenges(std::cmp::max(challenges, 1) as usize)
+    }
+
+    // generator of the order alpha_order subgroup
+    pub fn alpha(&self) -> F {
+        self.alpha_domain.generator()
+    }
+
+    // generator of the order beta_order subgroup
+    pub fn beta(&self) -> F {
+        self.beta_domain.generator()
+    }
+
+    pub fn alpha_order(&self) -> usize {
+        self.alpha_domain.size()
+    }
+
+    pub fn beta_order(&self) -> usize {
+        self.beta_domain.size()
+    }
+
+    // the order alpha_order subgroup
+    pub fn alpha_domain(&self) -> &EvaluationDomain<F> {
+        &self.alpha_domain
+    }
+
+    // the order batch_size subgroup of even powers of alpha, at which the inputs and
+    // outputs of an instance are placed
+    pub fn input_domain(&self) -> &EvaluationDomain<F> {
+        &self.input_domain
+    }
+
+    // the order beta_order subgroup
+    pub fn beta_domain(&self) -> &EvaluationDomain<F> {
+        &self.beta_domain
+    }
+
+    // number of noisy positions in the receiver's encoding
+    pub fn noisy(&self) -> usize {
+        self.noisy
+    }
+
+    // the FFT domains of the products in decoding and reconstruction, built on first use
+    pub fn mul_domains(&self) -> &MulDomains<F> {
+        &self.mul_domains
+    }
+
+    // number of OLEs per instance
+    pub fn batch_size(&self) -> usize {
+        self.alpha_order() / 2
+    }
+
+    // number of positions in the receiver's encoding
+    pub fn code_length(&self) -> usize {
+        self.alpha_order() + self.noisy
+    }
+
+    // number of challenge points of each consistency check
+    pub fn challenges(&self) -> usize {
+        self.challenges
+    }
+
+    // The soundness error of each consistency check is at most 2^-statistical_security()
+    pub fn statistical_security(&self) -> u32 {
+        self.bits_per_challenge() * self.challenges as u32
+    }
+
+    // lower bound on -log2(alpha_order / |F|), using |F| >= 2^SIZE_BITS
+    fn bits_per_challenge(&self) -> u32 {
+        let log_alpha_order = self.alpha_order().trailing_zeros();
+        F::SIZE_BITS.saturating_sub(log_alpha_order)
+    }
+}
+
+// The parameters given by the constants of the field, encoding on the whole beta domain
+impl<F: OleField> Default for OleParams<F> {
+    fn default() -> Self {
+        Self::from_generators(F::A, F::alpha(), F::B, F::beta(), F::B - F::A)
+    }
+}
+
+// Parameters of a VOLE instance, see vole. The sender's input polynomial has degree
+// < alpha_order on the whole alpha domain, so every instance gives alpha_order VOLEs,
+// and the encoding is the OLE receiver's on alpha_order + noisy powers of beta. VOLE
+// has no consistency checks, so there are no challenges.
+#[derive(Clone, Debug)]
+pub struct VoleParams<F: OleField> {
+    ole: OleParams<F>,
+}
+
+impl<F: OleField> VoleParams<F> {
+    // with the same constraints as OleParams::new
+    pub fn new(alpha_order: usize, beta_order: usize, noisy: usize) -> Result<Self, OleError> {
+        Ok(Self {
+            ole: OleParams::new(alpha_order, beta_order, noisy)?,
+        })
+    }
+
+    pub fn alpha_order(&self) -> usize {
+        self.ole.alpha_order()
+    }
+
+    pub fn beta_order(&self) -> usize {
+        self.ole.beta_order()
+    }
+
+    // the order alpha_order subgroup, at which the inputs and outputs are placed
+    pub fn alpha_domain(&self) -> &EvaluationDomain<F> {
+        self.ole.alpha_domain()
+    }
+
+    // the order beta_order subgroup
+    pub fn beta_domain(&self) -> &EvaluationDomain<F> {
+        self.ole.beta_domain()
+    }
+
+    // number of noisy positions in the sender's encoding
+    pub fn noisy(&self) -> usize {
+        self.ole.noisy()
+    }
+
+    // number of VOLEs per instance
+    pub fn batch_size(&self) -> usize {
+        self.alpha_order()
+    }
+
+    // number of positions in the sender's encoding
+    pub fn code_length(&self) -> usize {
+        self.ole.code_length()
+    }
+
+    // the commitment and decoding of an instance are those of OLE with these parameters
+    pub(crate) fn ole_params(&self) -> &OleParams<F> {
+        &self.ole
+    }
+}
+
+impl<F: OleField> Default for VoleParams<F> {
+    fn default() -> Self {
+        Self {
+            ole: OleParams::default(),
+        }
+    }
+}
+
+fn is_power_of_three(mut n: usize) -> bool {
+    if n < 3 {
+        return false;
+    }
+    while n.is_multiple_of(3) {
+        n /= 3;
+    }
+    return n == 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Fp;
+    use ff::Field;
+
+    #[test]
+    fn test_params_roots_of_unity() {
+        for (a, b) in [(2, 3), (64, 729), (2048, 59049)].iter() {
+            let params = OleParams::<Fp>::new(*a, *b, 1).unwrap();
+            assert_eq!(params.alpha().pow([*a as u64]), Fp::one());
+            assert_ne!(params.alpha().pow([(*a / 2) as u64]), Fp::one());
+            assert_eq!(params.beta().pow([*b as u64]), Fp::one());
+            assert_ne!(params.beta().pow([(*b / 3) as u64]), Fp::one());
+        }
+    }
+
+    #[test]
+    fn test_params_statistical_security() {
+        // 126 - 8 bits per challenge
+        let params = OleParams::<Fp>::default();
+        assert_eq!(params.challenges(), 1);
+        assert_eq!(params.statistical_security(), 118);
+
+        let params = params.with_statistical_security(128).unwrap();
+        assert_eq!(params.challenges(), 2);
+        assert_eq!(params.statistical_security(), 236);
+
+        let params = params.with_statistical_security(80).unwrap();
+        assert_eq!(params.challenges(), 1);
+
+        let params = OleParams::<Fp>::new(2048, 59049, 100)
+            .unwrap()
+            .with_challenges(3)
+            .unwrap();
+        assert_eq!(params.statistical_security(), 3 * 115);
+        assert!(params.with_challenges(0).is_err());
+    }
+
+    #[test]
+    fn test_params_invalid() {
+        let invalid = [
+            (96, 2187, 100),    // alpha order not a power of 2
+            (256, 2000, 100),   // beta order not a power of 3
+            (256, 2187, 0),     // no noisy positions
+            (256, 729, 500),    // code longer than the beta domain
+            (4096, 59049, 100), // 2^12 doesn't divide p-1
+            (256, 177147, 100), // 3^11 doesn't divide p-1
+        ];
+        for (a, b, noisy) in invalid.iter() {
+            assert!(matches!(
+                OleParams::<Fp>::new(*a, *b, *noisy),
+                Err(OleError::InvalidParams(_))
+            ));
+        }
+    }
+}
@@ -1,14 +1,42 @@
+use crate::fft::EvaluationDomain;
+use crate::field::{BatchField, FiniteField};
 use ff::Field;
+use std::sync::{Arc, Mutex};
+
+// products with a factor shorter than this are computed by schoolbook multiplication
+const KARATSUBA_MIN_LEN: usize = 32;
+
+// products of at least this length are computed by FFT, if the field has a subgroup of
+// order 2^a * 3^b at least the product length
+const FFT_MIN_LEN: usize = 256;
+
+// divisions with a quotient shorter than NEWTON_MIN_QUOTIENT_LEN or a divisor shorter
+// than NEWTON_MIN_DIVISOR_LEN are computed by schoolbook division
+const NEWTON_MIN_QUOTIENT_LEN: usize = 32;
+const NEWTON_MIN_DIVISOR_LEN: usize = 256;
 
 pub fn horner<F: Field>(coefficients: &[F], variable: &F) -> F {
     coefficients.iter().rev().fold(F::zero(), |acc, coeff| {
-        let mut a = acc.clone();
+        let mut a = acc;
         a.mul_assign(variable);
         a.add_assign(coeff);
         return a;
     })
 }
 
+// evaluates the polynomial at each of the points, running Horner's rule on all points
+// at once
+pub fn horner_batch<F: BatchField>(coefficients: &[F], points: &[F]) -> Vec<F> {
+    let mut result = vec![F::zero(); points.len()];
+    for coeff in coefficients.iter().rev() {
+        F::batch_mul(&mut result, points);
+        for r in result.iter_mut() {
+            r.add_assign(coeff);
+        }
+    }
+    return result;
+}
+
 // input: polynomials a and b in coefficient representation
 // outputs: (q, r) such that q*b + r = a, with deg(r) < deg(b)
 // precondition: leading coefficient of a and b (i.e. a[a.len() -1]) is nonzero
@@ -39,6 +67,259 @@ pub fn euclid_division<F: Field>(a: &[F], b: &[F]) -> (Vec<F>, Vec<F>) {
     return (q, r);
 }
 
+// euclid_division by Newton iteration: with rev(p) the coefficients of p in reverse
+// order, rev(q) = rev(a) / rev(b) mod x^(deg(a) - deg(b) + 1), and r = a - qb. Outputs
+// q with deg(a) - deg(b) + 1 coefficients and r without leading zeros
+// precondition: leading coefficient of a and b (i.e. a[a.len() -1]) is nonzero
+pub fn fast_euclid_division<F: FiniteField + BatchField>(a: &[F], b: &[F]) -> (Vec<F>, Vec<F>) {
+    fast_euclid_division_with(a, b, &MulDomains::new())
+}
+
+// fast_euclid_division with the FFT domains of its products taken from domains
+pub fn fast_euclid_division_with<F: FiniteField + BatchField>(
+    a: &[F],
+    b: &[F],
+    domains: &MulDomains<F>,
+) -> (Vec<F>, Vec<F>) {
+    let q_len = (a.len() + 1).saturating_sub(b.len());
+    if q_len < NEWTON_MIN_QUOTIENT_LEN || b.len() < NEWTON_MIN_DIVISOR_LEN {
+        return schoolbook_division(a, b);
+    }
+
+    let a_rev: Vec<F> = a.iter().rev().take(q_len).copied().collect();
+    let b_rev: Vec<F> = b.iter().rev().take(q_len).copied().collect();
+    let b_rev_inv = power_series_inverse(&b_rev, q_len, domains);
+    let mut q = poly_mul_with(&a_rev, &b_rev_inv, domains);
+    q.truncate(q_len);
+    q.reverse();
+
+    let qb = poly_mul_with(&q, b, domains);
+    let mut r = a[..b.len() - 1].to_vec();
+    F::batch_sub(&mut r, &qb[..b.len() - 1]);
+    return (q, trim_remainder(r));
+}
+
+// euclid_division subtracting the multiples of b with batch operations
+fn schoolbook_division<F: BatchField>(a: &[F], b: &[F]) -> (Vec<F>, Vec<F>) {
+    if a.len() < b.len() {
+        return (Vec::new(), a.to_vec());
+    }
+    let d = b.len() - 1;
+    let c = b[d].inverse().unwrap();
+    let mut q = vec![F::zero(); a.len() - d];
+    let mut r = a.to_vec();
+    let mut scaled = b.to_vec();
+    for i in (0..q.len()).rev() {
+        q[i] = r[i + d];
+        q[i].mul_assign(&c);
+        scaled.copy_from_slice(b);
+        F::batch_scale(&mut scaled, &q[i]);
+        F::batch_sub(&mut r[i..i + d + 1], &scaled);
+    }
+    r.truncate(d.max(1));
+    return (q, trim_remainder(r));
+}
+
+fn trim_remainder<F: Field>(mut r: Vec<F>) -> Vec<F> {
+    while r.len() > 1 && r[r.len() - 1].is_zero() {
+        r.pop();
+    }
+    return r;
+}
+
+// g such that fg = 1 mod x^len. If fg = 1 mod x^l, then g(2 - fg) = 1 mod x^2l, where
+// fg - 1 = x^l e mod x^2l, so each step appends the next l coefficients -ge mod x^l
+// precondition: f[0] is nonzero
+fn power_series_inverse<F: FiniteField + BatchField>(
+    f: &[F],
+    len: usize,
+    domains: &MulDomains<F>,
+) -> Vec<F> {
+    let mut g = vec![f[0].inverse().unwrap()];
+    while g.len() < len {
+        let l = g.len();
+        let next_len = (2 * l).min(len);
+        let mut fg = poly_mul_with(&f[..f.len().min(next_len)], &g, domains);
+        fg.resize(next_len.max(fg.len()), F::zero());
+        let ge = poly_mul_with(&g[..next_len - l], &fg[l..next_len], domains);
+        g.resize(next_len, F::zero());
+        F::batch_sub(&mut g[l..], &ge[..next_len - l]);
+    }
+    return g;
+}
+
+// product of the polynomials a and b in coefficient representation. Long products go
+// through an FFT over the smallest subgroup of order 2^i * 3^j holding the product,
+// shorter ones or fields without such a subgroup through Karatsuba, down to schoolbook
+// multiplication. The product with an empty polynomial is empty
+pub fn poly_mul<F: FiniteField + BatchField>(a: &[F], b: &[F]) -> Vec<F> {
+    poly_mul_with(a, b, &MulDomains::new())
+}
+
+// poly_mul with the FFT domain taken from domains, for repeated products of a length
+pub fn poly_mul_with<F: FiniteField + BatchField>(
+    a: &[F],
+    b: &[F],
+    domains: &MulDomains<F>,
+) -> Vec<F> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let len = a.len() + b.len() - 1;
+    if a.len().min(b.len()) >= KARATSUBA_MIN_LEN && len >= FFT_MIN_LEN {
+        if let Some(domain) = domains.get(len) {
+            return fft_mul(a, b, &domain);
+        }
+    }
+    return karatsuba_mul(a, b);
+}
+
+// The FFT domains of poly_mul_with, by product length. Each is built on the first
+// product of its length, which costs as much as several FFTs on it, and shared after.
+// Products of lengths with a domain of the same size share it too.
+pub struct MulDomains<F: BatchField> {
+    domains: Mutex<Vec<ProductDomain<F>>>,
+}
+
+// a product length and its domain, None without a subgroup for it
+type ProductDomain<F> = (usize, Option<Arc<EvaluationDomain<F>>>);
+
+impl<F: FiniteField + BatchField> MulDomains<F> {
+    pub fn new() -> Self {
+        Self {
+            domains: Mutex::new(Vec::new()),
+        }
+    }
+
+    // the domain for products of length len, None if F has no subgroup for it
+    fn get(&self, len: usize) -> Option<Arc<EvaluationDomain<F>>> {
+        let mut domains = self.domains.lock().unwrap();
+        if let Some((_, domain)) = domains.iter().find(|(l, _)| *l == len) {
+            return domain.clone();
+        }
+        let domain = fft_size::<F>(len).map(|(n, omega)| {
+            let shared = domains
+                .iter()
+                .filter_map(|(_, domain)| domain.as_ref())
+                .find(|domain| domain.size() == n)
+                .cloned();
+            shared.unwrap_or_else(|| Arc::new(EvaluationDomain::new(n, omega)))
+        });
+        domains.push((len, domain.clone()));
+        return domain;
+    }
+}
+
+impl<F: FiniteField + BatchField> Default for MulDomains<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the clone shares the domains built so far
+impl<F: BatchField> Clone for MulDomains<F> {
+    fn clone(&self) -> Self {
+        Self {
+            domains: Mutex::new(self.domains.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl<F: BatchField> std::fmt::Debug for MulDomains<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let lens: Vec<usize> = self.domains.lock().unwrap().iter().map(|e| e.0).collect();
+        write!(f, "MulDomains({:?})", lens)
+    }
+}
+
+// smallest n = 2^i * 3^j with n >= len such that F has a subgroup of order n, with its
+// generator
+fn fft_size<F: FiniteField>(len: usize) -> Option<(usize, F)> {
+    let mut sizes = Vec::new();
+    let mut power3 = 1usize;
+    loop {
+        let mut n = power3;
+        while n < len {
+            n *= 2;
+        }
+        sizes.push(n);
+        if power3 >= len {
+            break;
+        }
+        power3 *= 3;
+    }
+    sizes.sort();
+    return sizes
+        .into_iter()
+        .find_map(|n| Some((n, F::subgroup_generator(n as u64)?)));
+}
+
+// product by pointwise multiplication of the evaluations on the domain
+// Precondition: a.len() + b.len() - 1 <= domain.size()
+fn fft_mul<F: BatchField>(a: &[F], b: &[F], domain: &EvaluationDomain<F>) -> Vec<F> {
+    let mut a_points = a.to_vec();
+    a_points.resize(domain.size(), F::zero());
+    domain.fft(&mut a_points);
+    let mut b_points = b.to_vec();
+    b_points.resize(domain.size(), F::zero());
+    domain.fft(&mut b_points);
+    F::batch_mul(&mut a_points, &b_points);
+    domain.fft_inverse(&mut a_points);
+    a_points.truncate(a.len() + b.len() - 1);
+    return a_points;
+}
+
+// With a = a0 + x^m a1 and b = b0 + x^m b1 for m half their length,
+// ab = a0b0 + x^m ((a0 + a1)(b0 + b1) - a0b0 - a1b1) + x^2m a1b1. Factors of different
+// lengths are multiplied by chunks of the longer one of the length of the shorter one
+fn karatsuba_mul<F: BatchField>(a: &[F], b: &[F]) -> Vec<F> {
+    if a.len() < b.len() {
+        return karatsuba_mul(b, a);
+    }
+    if b.len() < KARATSUBA_MIN_LEN {
+        return schoolbook_mul(b, a);
+    }
+    let mut prod = vec![F::zero(); a.len() + b.len() - 1];
+    if a.len() > b.len() {
+        for (i, chunk) in a.chunks(b.len()).enumerate() {
+            let chunk_prod = karatsuba_mul(chunk, b);
+            let start = i * b.len();
+            F::batch_add(&mut prod[start..start + chunk_prod.len()], &chunk_prod);
+        }
+        return prod;
+    }
+
+    let m = a.len() / 2;
+    let (a0, a1) = a.split_at(m);
+    let (b0, b1) = b.split_at(m);
+    let low = karatsuba_mul(a0, b0);
+    let high = karatsuba_mul(a1, b1);
+    let mut a_sum = a1.to_vec();
+    F::batch_add(&mut a_sum[..m], a0);
+    let mut b_sum = b1.to_vec();
+    F::batch_add(&mut b_sum[..m], b0);
+    let mut mid = karatsuba_mul(&a_sum, &b_sum);
+    F::batch_sub(&mut mid[..low.len()], &low);
+    F::batch_sub(&mut mid, &high);
+
+    F::batch_add(&mut prod[..low.len()], &low);
+    F::batch_add(&mut prod[m..m + mid.len()], &mid);
+    F::batch_add(&mut prod[2 * m..], &high);
+    return prod;
+}
+
+// adds b scaled by each coefficient of a, so a should be the shorter factor
+fn schoolbook_mul<F: BatchField>(a: &[F], b: &[F]) -> Vec<F> {
+    let mut prod = vec![F::zero(); a.len() + b.len() - 1];
+    let mut scaled = b.to_vec();
+    for (i, c) in a.iter().enumerate() {
+        scaled.copy_from_slice(b);
+        F::batch_scale(&mut scaled, c);
+        F::batch_add(&mut prod[i..i + b.len()], &scaled);
+    }
+    return prod;
+}
+
 #[cfg(test)]
 fn naive_poly_mult<F: Field>(a: &[F], b: &[F]) -> Vec<F> {
     let mut prod = vec![F::zero(); a.len() + b.len() - 1];
@@ -109,36 +390,63 @@ pub fn lagrangian_interpolation<F: Field>(xs: &[F], ys: &[F]) -> F {
 mod tests {
     use super::*;
     use crate::field::{Fp, FpRepr, OleField};
+    use crate::field64::Fp64;
     use ff::PrimeField;
     use rand;
     use rand::seq::IteratorRandom;
 
     #[test]
     fn test_lagrangian_interpolation() {
+        check_lagrangian_interpolation::<Fp>();
+        check_lagrangian_interpolation::<Fp64>();
+    }
+
+    fn check_lagrangian_interpolation<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let mut coeffs: Vec<Fp> = (0..Fp::A / 2).map(|_| Fp::random(&mut rng)).collect();
-        coeffs.resize_with(Fp::A, Fp::zero);
+        let mut coeffs: Vec<F> = (0..F::A / 2).map(|_| F::random(&mut rng)).collect();
+        coeffs.resize_with(F::A, F::zero);
 
         let mut ys = coeffs.to_vec();
-        Fp::fft2(&mut ys, &Fp::alpha());
+        F::fft2(&mut ys, &F::alpha());
 
-        let xs = (0..Fp::A).choose_multiple(&mut rng, Fp::A / 2);
-        let ys_: Vec<Fp> = xs.iter().map(|i| ys[*i]).collect();
-        let xs_: Vec<Fp> = xs.iter().map(|i| Fp::alpha().pow([*i as u64])).collect();
+        let xs = (0..F::A).choose_multiple(&mut rng, F::A / 2);
+        let ys_: Vec<F> = xs.iter().map(|i| ys[*i]).collect();
+        let xs_: Vec<F> = xs.iter().map(|i| F::alpha().pow([*i as u64])).collect();
 
         let result = lagrangian_interpolation(&xs_, &ys_);
         assert_eq!(result, coeffs[0]);
     }
 
+    #[test]
+    fn test_horner_batch() {
+        check_horner_batch::<Fp>();
+        check_horner_batch::<Fp64>();
+    }
+
+    fn check_horner_batch<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let coeffs: Vec<F> = (0..101).map(|_| F::random(&mut rng)).collect();
+        let points: Vec<F> = (0..10).map(|_| F::random(&mut rng)).collect();
+        let result = horner_batch(&coeffs, &points);
+        for (y, x) in result.iter().zip(&points) {
+            assert_eq!(*y, horner(&coeffs, x));
+        }
+    }
+
     #[test]
     fn test_poly_add() {
+        check_poly_add::<Fp>();
+        check_poly_add::<Fp64>();
+    }
+
+    fn check_poly_add<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let a: Vec<Fp> = (0..101).map(|_| Fp::random(&mut rng)).collect();
-        let b: Vec<Fp> = (0..22).map(|_| Fp::random(&mut rng)).collect();
+        let a: Vec<F> = (0..101).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..22).map(|_| F::random(&mut rng)).collect();
         let mut c = a.clone();
         poly_add(&mut c, &b);
         for _ in 0..1000 {
-            let point = Fp::random(&mut rng);
+            let point = F::random(&mut rng);
             let mut a_ = horner(&a, &point);
             let b_ = horner(&b, &point);
             let c_ = horner(&c, &point);
@@ -150,12 +458,17 @@ mod tests {
 
     #[test]
     fn test_naive_poly_mult() {
+        check_naive_poly_mult::<Fp>();
+        check_naive_poly_mult::<Fp64>();
+    }
+
+    fn check_naive_poly_mult<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let a: Vec<Fp> = (0..101).map(|_| Fp::random(&mut rng)).collect();
-        let b: Vec<Fp> = (0..22).map(|_| Fp::random(&mut rng)).collect();
+        let a: Vec<F> = (0..101).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..22).map(|_| F::random(&mut rng)).collect();
         let prod = naive_poly_mult(&a, &b);
         for _ in 0..1000 {
-            let point = Fp::random(&mut rng);
+            let point = F::random(&mut rng);
             let mut a_ = horner(&a, &point);
             let b_ = horner(&b, &point);
             let c_ = horner(&prod, &point);
@@ -165,11 +478,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_poly_mul() {
+        check_poly_mul::<Fp>();
+        check_poly_mul::<Fp64>();
+    }
+
+    fn check_poly_mul<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        // schoolbook, Karatsuba on balanced and unbalanced factors, and FFT
+        for (na, nb) in [
+            (1, 1),
+            (1, 40),
+            (31, 32),
+            (33, 33),
+            (100, 7),
+            (200, 300),
+            (700, 1000),
+        ]
+        .iter()
+        {
+            let a: Vec<F> = (0..*na).map(|_| F::random(&mut rng)).collect();
+            let b: Vec<F> = (0..*nb).map(|_| F::random(&mut rng)).collect();
+            assert_eq!(poly_mul(&a, &b), naive_poly_mult(&a, &b));
+        }
+        assert!(poly_mul::<F>(&[], &[F::one()]).is_empty());
+
+        // a shared cache gives the same products, with one domain for each size
+        let domains = MulDomains::new();
+        for (na, nb) in [(200, 300), (300, 200), (250, 250), (700, 1000)].iter() {
+            let a: Vec<F> = (0..*na).map(|_| F::random(&mut rng)).collect();
+            let b: Vec<F> = (0..*nb).map(|_| F::random(&mut rng)).collect();
+            assert_eq!(poly_mul_with(&a, &b, &domains), naive_poly_mult(&a, &b));
+        }
+        let domain = domains.get(499).unwrap();
+        assert_eq!(domain.size(), 512);
+        assert!(Arc::ptr_eq(&domain, &domains.get(500).unwrap()));
+
+        // product of degree ~10^5 over a domain of order 2^i * 3^j
+        let a: Vec<F> = (0..40000).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..60001).map(|_| F::random(&mut rng)).collect();
+        let prod = poly_mul(&a, &b);
+        assert_eq!(prod.len(), a.len() + b.len() - 1);
+        for _ in 0..10 {
+            let point = F::random(&mut rng);
+            let mut expected = horner(&a, &point);
+            expected.mul_assign(&horner(&b, &point));
+            assert_eq!(horner(&prod, &point), expected);
+        }
+    }
+
     #[test]
     fn test_euclid_division() {
+        check_euclid_division::<Fp>();
+        check_euclid_division::<Fp64>();
+    }
+
+    fn check_euclid_division<F: OleField>() {
         let mut rng = rand::thread_rng();
-        let a: Vec<Fp> = (0..83).map(|_| Fp::random(&mut rng)).collect();
-        let b: Vec<Fp> = (0..22).map(|_| Fp::random(&mut rng)).collect();
+        let a: Vec<F> = (0..83).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..22).map(|_| F::random(&mut rng)).collect();
         let (q, r) = euclid_division(&a, &b);
         let mut actual = naive_poly_mult(&q, &b);
         println!("{:?}", q);
@@ -181,6 +549,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fast_euclid_division() {
+        check_fast_euclid_division::<Fp>();
+        check_fast_euclid_division::<Fp64>();
+    }
+
+    fn check_fast_euclid_division<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        // schoolbook and Newton division, including the sizes of decode_reed_solomon
+        for (na, nb) in [
+            (1usize, 1usize),
+            (50, 1),
+            (20, 30),
+            (83, 22),
+            (300, 256),
+            (1000, 936),
+            (2187, 256),
+            (2187, 1932),
+            (5000, 2048),
+        ]
+        .iter()
+        {
+            let a: Vec<F> = (0..*na).map(|_| F::random(&mut rng)).collect();
+            let b: Vec<F> = (0..*nb).map(|_| F::random(&mut rng)).collect();
+            let (q, r) = fast_euclid_division(&a, &b);
+            assert_eq!(q.len(), (na + 1).saturating_sub(*nb));
+            assert!(r.len() < *nb || na < nb || r == vec![F::zero()]);
+            if *nb > 1 && na >= nb {
+                let (mut q_, r_) = euclid_division(&a, &b);
+                q_.truncate(q.len());
+                assert_eq!(q, q_);
+                assert_eq!(r, r_);
+            }
+
+            let mut actual = poly_mul(&q, &b);
+            actual.resize(*na, F::zero());
+            poly_add(&mut actual, &r);
+            assert_eq!(actual, a);
+        }
+
+        // exact division, where euclid_division would run out of nonzero remainder
+        let b: Vec<F> = (0..300).map(|_| F::random(&mut rng)).collect();
+        let q: Vec<F> = (0..400).map(|_| F::random(&mut rng)).collect();
+        assert_eq!(
+            fast_euclid_division(&poly_mul(&q, &b), &b),
+            (q, vec![F::zero()])
+        );
+    }
+
     #[test]
     fn test_horner() {
         let coeffs = [
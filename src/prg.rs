@@ -0,0 +1,121 @@
+use crate::field::FiniteField;
+use scuttlebutt::{Aes128, Block};
+
+// Expands a seed into len uniformly random field elements, so that a party can send the
+// seed in place of a random vector. Only public random vectors qualify, such as the
+// challenges of the consistency checks: the masks, sharing coefficients and encoding
+// padding of an OLE instance must stay hidden from the other party.
+//
+// The byte stream is AES-128 in counter mode under the seed,
+// AES_seed(0) || AES_seed(1) || ... with the counter as 16 little-endian bytes. Every
+// candidate takes the next F::BYTES bytes of the stream and is rejected if it encodes
+// no element, see FiniteField::from_random_bytes.
+pub fn expand_field_vec<F: FiniteField>(seed: &Block, len: usize) -> Vec<F> {
+    let mut stream = AesStream::new(seed);
+    let mut bytes = vec![0u8; F::BYTES];
+    let mut result = Vec::with_capacity(len);
+    while result.len() < len {
+        stream.fill_bytes(&mut bytes);
+        if let Some(x) = F::from_random_bytes(&mut bytes) {
+            result.push(x);
+        }
+    }
+    return result;
+}
+
+struct AesStream {
+    aes: Aes128,
+    counter: u128,
+    block: [u8; 16],
+    used: usize, // bytes of block already output
+}
+
+impl AesStream {
+    fn new(seed: &Block) -> Self {
+        Self {
+            aes: Aes128::new(*seed),
+            counter: 0,
+            block: [0u8; 16],
+            used: 16,
+        }
+    }
+
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for b in bytes.iter_mut() {
+            if self.used == 16 {
+                let counter = Block::from(self.counter.to_le_bytes());
+                self.block = self.aes.encrypt(counter).into();
+                self.counter += 1;
+                self.used = 0;
+            }
+            *b = self.block[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::tests::Fq2;
+    use crate::field::Fp;
+    use crate::field64::Fp64;
+    use ff::PrimeField;
+
+    fn seed() -> Block {
+        let mut bytes = [0u8; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        Block::from(bytes)
+    }
+
+    #[test]
+    fn test_expand_field_vec_known_answers() {
+        // the 3rd candidate under seed 00 01 .. 0f encodes an integer >= q, and is
+        // rejected
+        let expected: Vec<Fp> = [
+            "3473005186362286825626814350837906659",
+            "51112080585025629406493486548674382587",
+            "151815805013836392824847365387580979340",
+            "44581536855133321995950038982442454256",
+        ]
+        .iter()
+        .map(|x| Fp::from_str(x).unwrap())
+        .collect();
+        assert_eq!(expand_field_vec::<Fp>(&seed(), 4), expected);
+
+        let expected: Vec<Fp> = [
+            "61368827288258104251737371505591052646",
+            "110406707629629219392888704865949741383",
+        ]
+        .iter()
+        .map(|x| Fp::from_str(x).unwrap())
+        .collect();
+        assert_eq!(expand_field_vec::<Fp>(&Block::default(), 2), expected);
+
+        let expected: Vec<Fp64> = [
+            "169887221866537414",
+            "8779988069026713455",
+            "2343979421374053603",
+            "188271988405370778",
+        ]
+        .iter()
+        .map(|x| Fp64::from_str(x).unwrap())
+        .collect();
+        assert_eq!(expand_field_vec::<Fp64>(&seed(), 4), expected);
+    }
+
+    #[test]
+    fn test_expand_field_vec() {
+        let long: Vec<Fp> = expand_field_vec(&seed(), 1000);
+        let short: Vec<Fp> = expand_field_vec(&seed(), 10);
+        assert_eq!(&long[..10], &short[..]);
+        assert_ne!(expand_field_vec::<Fp>(&Block::default(), 10), short);
+        assert!(expand_field_vec::<Fp>(&seed(), 0).is_empty());
+
+        let long: Vec<Fq2> = expand_field_vec(&seed(), 100);
+        let short: Vec<Fq2> = expand_field_vec(&seed(), 10);
+        assert_eq!(&long[..10], &short[..]);
+    }
+}
@@ -1,22 +1,45 @@
+use crate::fft::EvaluationDomain;
 use crate::field::OleField;
-use crate::poly::{euclid_division, poly_from_roots};
-use rand;
+use crate::poly::{fast_euclid_division_with, poly_from_roots, MulDomains};
+use rand::{CryptoRng, Rng};
 
-pub fn share<F: OleField>(secret: &F, n: u64, rho: u64, omega: &F) -> Vec<F> {
-    let mut rng = rand::thread_rng();
-    let mut coeffs: Vec<F> = (0..rho).map(|_| F::random(&mut rng)).collect();
-    coeffs.resize_with(n as usize, F::zero);
+// shares of the secret at every element of the domain, any rho of which reconstruct it.
+// The random coefficients of the sharing polynomial are drawn from rng
+pub fn share<F: OleField, Crng: CryptoRng + Rng>(
+    secret: &F,
+    rho: u64,
+    domain: &EvaluationDomain<F>,
+    rng: &mut Crng,
+) -> Vec<F> {
+    let mut coeffs: Vec<F> = (0..rho).map(|_| F::random(rng)).collect();
+    coeffs.resize_with(domain.size(), F::zero);
     coeffs[0] = *secret;
-    F::fft3(&mut coeffs, omega);
+    domain.fft(&mut coeffs);
     return coeffs;
 }
 
-pub fn reconstruct<F: OleField>(indices: &[usize], shares: &[F], n: u64, rho: u64, omega: &F) -> F {
+pub fn reconstruct<F: OleField>(
+    indices: &[usize],
+    shares: &[F],
+    rho: u64,
+    domain: &EvaluationDomain<F>,
+) -> F {
+    reconstruct_with(indices, shares, rho, domain, &MulDomains::new())
+}
+
+// reconstruct with the FFT domains of the division taken from mul_domains
+pub fn reconstruct_with<F: OleField>(
+    indices: &[usize],
+    shares: &[F],
+    rho: u64,
+    domain: &EvaluationDomain<F>,
+    mul_domains: &MulDomains<F>,
+) -> F {
     assert_eq!(indices.len(), shares.len());
     let mut i = 0usize;
-    let mut points_with_error: Vec<F> = (0..n)
+    let mut points_with_error: Vec<F> = (0..domain.size())
         .map(|j| {
-            if (i < rho as usize) && (j == (indices[i] as u64)) {
+            if (i < rho as usize) && (j == indices[i]) {
                 i += 1;
                 shares[i - 1]
             } else {
@@ -24,10 +47,10 @@ pub fn reconstruct<F: OleField>(indices: &[usize], shares: &[F], n: u64, rho: u6
             }
         })
         .collect();
-    let roots: Vec<F> = indices.iter().map(|idx| omega.pow([*idx as u64])).collect();
-    F::fft3_inverse(&mut points_with_error, omega);
+    let roots: Vec<F> = indices.iter().map(|idx| domain.element(*idx)).collect();
+    domain.fft_inverse(&mut points_with_error);
     let b = poly_from_roots(&roots);
-    let (_, r) = euclid_division(&points_with_error, &b);
+    let (_, r) = fast_euclid_division_with(&points_with_error, &b, mul_domains);
     return r[0];
 }
 
@@ -44,13 +67,13 @@ mod tests {
         let secret = Fp::random(&mut rng);
         let n = 3u64.pow(7);
         let rho = n - 2u64.pow(8);
-        let omega = Fp::beta();
+        let domain = EvaluationDomain::new(n as usize, Fp::beta());
 
-        let shares = share(&secret, n, rho, &omega);
+        let shares = share(&secret, rho, &domain, &mut rng);
         let mut indices: Vec<usize> = (0..(n as usize)).choose_multiple(&mut rng, rho as usize);
         indices.sort();
         let myshares: Vec<Fp> = indices.iter().map(|i| shares[*i]).collect();
-        let reconstructed = reconstruct(&indices, &myshares, n, rho, &omega);
+        let reconstructed = reconstruct(&indices, &myshares, rho, &domain);
 
         // println!("secret: {:?}\nreconstr: {:?}", secret, reconstructed);
 
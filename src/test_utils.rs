@@ -0,0 +1,30 @@
+// The two party harness of the protocol tests
+
+use scuttlebutt::channel::Channel;
+use std::{
+    io::{BufReader, BufWriter},
+    os::unix::net::UnixStream,
+};
+
+pub(crate) type TestChannel = Channel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+// a buffered channel over one end of a UnixStream::pair
+pub(crate) fn to_channel(stream: UnixStream) -> TestChannel {
+    let reader = BufReader::new(stream.try_clone().unwrap());
+    let writer = BufWriter::new(stream);
+    return Channel::new(reader, writer);
+}
+
+// Runs first on a new thread and second on this one, connected by a channel, and
+// returns both results. A panic of either party fails the test.
+pub(crate) fn run_parties<T, U, F1, F2>(first: F1, second: F2) -> (T, U)
+where
+    T: Send + 'static,
+    F1: FnOnce(TestChannel) -> T + Send + 'static,
+    F2: FnOnce(TestChannel) -> U,
+{
+    let (first_stream, second_stream) = UnixStream::pair().unwrap();
+    let handle = std::thread::spawn(move || first(to_channel(first_stream)));
+    let second_result = second(to_channel(second_stream));
+    return (handle.join().unwrap(), second_result);
+}
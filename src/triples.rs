@@ -0,0 +1,397 @@
+use crate::error::OleError;
+use crate::field::OleField;
+use crate::ole::{
+    read_elements, to_bytes, to_elements, write_elements, OleReceiver, OleSender, Receiver, Sender,
+};
+use crate::params::OleParams;
+use crate::prg::expand_field_vec;
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{channel::AbstractChannel, Block};
+use sha2::{Digest, Sha256};
+
+// Beaver multiplication triples from OLE.
+//
+// Each party picks random shares a_i, b_i. The cross terms of
+// c = (a_1 + a_2)*(b_1 + b_2) are computed by two OLEs, one in each direction: in the
+// first the first party inputs (a_1, s_1) and the second party x = b_2, in the second
+// the roles are swapped. Each party adds -s_i to its share of c.
+//
+// Shares are unauthenticated, so the sacrifice check catches incorrect triples, but
+// not a party lying about its shares of rho and sigma. The shares of the final check
+// values t are committed to by SHA-256 before either party opens them, so neither
+// party can pick its share after seeing the other's to make the check pass.
+
+// A party's additive share of a triple (a, b, c = a*b)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triple<F: OleField> {
+    pub a: F,
+    pub b: F,
+    pub c: F,
+}
+
+pub struct TripleGenerator {
+    sender: OleSender,
+    receiver: OleReceiver,
+    // the first party acts as OLE sender first, and writes first when exchanging values
+    first: bool,
+}
+
+impl TripleGenerator {
+    // Sets up OLE in both directions. Exactly one of the two parties must pass
+    // first = true
+    pub fn init<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        first: bool,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Self, OleError> {
+        let (sender, receiver) = if first {
+            let sender = OleSender::init(channel, rng)?;
+            (sender, OleReceiver::init(channel, rng)?)
+        } else {
+            let receiver = OleReceiver::init(channel, rng)?;
+            (OleSender::init(channel, rng)?, receiver)
+        };
+        Ok(Self {
+            sender: sender,
+            receiver: receiver,
+            first: first,
+        })
+    }
+
+    // Generates shares of n triples, with the default OLE parameters of the field
+    pub fn generate<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<Triple<F>>, OleError> {
+        self.generate_with_params(n, &OleParams::default(), channel, rng)
+    }
+
+    // Generates shares of n triples by OLEs with params, which both parties must use
+    pub fn generate_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<Triple<F>>, OleError> {
+        let a: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+        let s: Vec<F> = (0..n).map(|_| F::random(rng)).collect();
+
+        // y_i = a_other*b_i + s_other
+        let y = if self.first {
+            self.sender
+                .input_with_params(&a, &s, params, channel, rng)?;
+            self.receiver.input_with_params(&b, params, channel, rng)?
+        } else {
+            let y = self.receiver.input_with_params(&b, params, channel, rng)?;
+            self.sender
+                .input_with_params(&a, &s, params, channel, rng)?;
+            y
+        };
+
+        let triples = (0..n)
+            .map(|i| {
+                let mut c = a[i];
+                c.mul_assign(&b[i]);
+                c.add_assign(&y[i]);
+                c.sub_assign(&s[i]);
+                Triple {
+                    a: a[i],
+                    b: b[i],
+                    c: c,
+                }
+            })
+            .collect();
+        return Ok(triples);
+    }
+
+    // Generates shares of n triples, checked by sacrificing another n triples
+    pub fn generate_checked<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<Triple<F>>, OleError> {
+        self.generate_checked_with_params(n, &OleParams::default(), channel, rng)
+    }
+
+    // generate_checked by OLEs with params, which both parties must use
+    pub fn generate_checked_with_params<F, C, Crng>(
+        &mut self,
+        n: usize,
+        params: &OleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<Triple<F>>, OleError>
+    where
+        F: OleField,
+        C: AbstractChannel,
+        Crng: CryptoRng + Rng,
+    {
+        let mut triples = self.generate_with_params(2 * n, params, channel, rng)?;
+        let sacrificed = triples.split_off(n);
+        self.sacrifice(&triples, &sacrificed, channel, rng)?;
+        return Ok(triples);
+    }
+
+    // Checks each triple (a, b, c) using the corresponding sacrificed triple (x, y, z),
+    // which must not be used afterwards. For a random r, the parties open
+    // rho = r*a - x and sigma = b - y, and then
+    // t = r*c - z - sigma*x - rho*y - sigma*rho, which is zero for correct triples.
+    // The shares of t are exchanged by exchange_committed
+    pub fn sacrifice<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        triples: &[Triple<F>],
+        sacrificed: &[Triple<F>],
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        assert_eq!(triples.len(), sacrificed.len());
+
+        let r: Vec<F> = expand_field_vec(&self.coin_toss(channel, rng)?, triples.len());
+
+        let mut opening = Vec::with_capacity(2 * triples.len());
+        for ((t, s), ri) in triples.iter().zip(sacrificed).zip(&r) {
+            let mut rho = t.a;
+            rho.mul_assign(ri);
+            rho.sub_assign(&s.a);
+            let mut sigma = t.b;
+            sigma.sub_assign(&s.b);
+            opening.push(rho);
+            opening.push(sigma);
+        }
+        let other = self.exchange(&opening, channel)?;
+        for (v, w) in opening.iter_mut().zip(&other) {
+            v.add_assign(w);
+        }
+
+        let mut check = Vec::with_capacity(triples.len());
+        for (i, ((t, s), ri)) in triples.iter().zip(sacrificed).zip(&r).enumerate() {
+            let (rho, sigma) = (opening[2 * i], opening[2 * i + 1]);
+            let mut ti = t.c;
+            ti.mul_assign(ri);
+            ti.sub_assign(&s.c);
+            let mut tmp = sigma;
+            tmp.mul_assign(&s.a);
+            ti.sub_assign(&tmp);
+            tmp = rho;
+            tmp.mul_assign(&s.b);
+            ti.sub_assign(&tmp);
+            if self.first {
+                tmp = sigma;
+                tmp.mul_assign(&rho);
+                ti.sub_assign(&tmp);
+            }
+            check.push(ti);
+        }
+        let other = self.exchange_committed(&to_bytes(&check), channel, rng)?;
+        let other: Vec<F> = to_elements(&other)?;
+        for (v, w) in check.iter().zip(&other) {
+            let mut t = *v;
+            t.add_assign(w);
+            if !t.is_zero() {
+                return Err(OleError::TripleCheckFailed);
+            }
+        }
+        return Ok(());
+    }
+
+    // Sends this party's values and receives the other party's, the first party writes
+    // first so that neither side blocks on a full channel
+    fn exchange<F: OleField, C: AbstractChannel>(
+        &self,
+        values: &[F],
+        channel: &mut C,
+    ) -> Result<Vec<F>, OleError> {
+        if self.first {
+            write_elements(channel, values)?;
+            channel.flush()?;
+        }
+        let other = read_elements(channel, values.len())?;
+        if !self.first {
+            write_elements(channel, values)?;
+            channel.flush()?;
+        }
+        return Ok(other);
+    }
+
+    // Jointly samples a random seed, the xor of a random block of each party
+    fn coin_toss<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &self,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Block, OleError> {
+        let seed = rng.gen::<Block>();
+        let other = self.exchange_committed(seed.as_ref(), channel, rng)?;
+        let mut other_seed = Block::default();
+        other_seed.as_mut().copy_from_slice(&other);
+        return Ok(seed ^ other_seed);
+    }
+
+    // Exchanges messages of equal length such that neither party learns the other's
+    // message before fixing its own: both commit to nonce || message by SHA-256, and
+    // open only after receiving the other's commitment
+    fn exchange_committed<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &self,
+        message: &[u8],
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<u8>, OleError> {
+        let mut opening = rng.gen::<Block>().as_ref().to_vec();
+        opening.extend_from_slice(message);
+        let com = Sha256::digest(&opening);
+
+        let mut other_com = [0u8; 32];
+        if self.first {
+            channel.write_bytes(&com)?;
+            channel.flush()?;
+            channel.read_bytes(&mut other_com)?;
+            channel.write_bytes(&opening)?;
+            channel.flush()?;
+        } else {
+            channel.read_bytes(&mut other_com)?;
+            channel.write_bytes(&com)?;
+            channel.flush()?;
+        }
+        let mut other_opening = vec![0u8; opening.len()];
+        channel.read_bytes(&mut other_opening)?;
+        if !self.first {
+            channel.write_bytes(&opening)?;
+            channel.flush()?;
+        }
+
+        if Sha256::digest(&other_opening)[..] != other_com[..] {
+            return Err(OleError::CommitmentMismatch);
+        }
+        return Ok(other_opening.split_off(16));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::tests::Fq2;
+    use crate::field::Fp;
+    use crate::field64::Fp64;
+    use crate::test_utils::{run_parties, TestChannel};
+    use ff::Field;
+    use rand;
+
+    // runs f for both parties, returning (first, second)
+    fn run_both<T, Fun>(f: Fun) -> (T, T)
+    where
+        T: Send + 'static,
+        Fun: Fn(bool, &mut TestChannel) -> T + Send + Copy + 'static,
+    {
+        return run_parties(
+            move |mut channel| f(true, &mut channel),
+            move |mut channel| f(false, &mut channel),
+        );
+    }
+
+    fn open<F: OleField>(first: &Triple<F>, second: &Triple<F>) -> (F, F, F) {
+        let mut a = first.a;
+        a.add_assign(&second.a);
+        let mut b = first.b;
+        b.add_assign(&second.b);
+        let mut c = first.c;
+        c.add_assign(&second.c);
+        return (a, b, c);
+    }
+
+    #[test]
+    fn test_triples() {
+        check_triples::<Fp>();
+        check_triples::<Fp64>();
+        check_triples::<Fq2>();
+    }
+
+    fn check_triples<F: OleField>() {
+        let n = F::A / 2 + 10;
+        let (first, second) = run_both(move |is_first, channel| {
+            let mut rng = rand::thread_rng();
+            let mut generator = TripleGenerator::init(is_first, channel, &mut rng).unwrap();
+            let triples: Vec<Triple<F>> = generator.generate(n, channel, &mut rng).unwrap();
+            triples
+        });
+
+        assert_eq!(first.len(), n);
+        assert_eq!(second.len(), n);
+        for (t1, t2) in first.iter().zip(&second) {
+            let (a, b, c) = open(t1, t2);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            assert_eq!(c, ab);
+        }
+    }
+
+    #[test]
+    fn test_generate_checked() {
+        check_generate_checked::<Fp>();
+        check_generate_checked::<Fp64>();
+        check_generate_checked::<Fq2>();
+    }
+
+    fn check_generate_checked<F: OleField>() {
+        let n = 20;
+        let (first, second) = run_both(move |is_first, channel| {
+            let mut rng = rand::thread_rng();
+            let mut generator = TripleGenerator::init(is_first, channel, &mut rng).unwrap();
+            let triples: Vec<Triple<F>> = generator.generate_checked(n, channel, &mut rng).unwrap();
+            triples
+        });
+
+        assert_eq!(first.len(), n);
+        for (t1, t2) in first.iter().zip(&second) {
+            let (a, b, c) = open(t1, t2);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            assert_eq!(c, ab);
+        }
+    }
+
+    #[test]
+    fn test_triples_params() {
+        // instances of 4 triples, the last one partly padding
+        let n = 4 * 5 + 3;
+        let (first, second) = run_both(move |is_first, channel| {
+            let mut rng = rand::thread_rng();
+            let params = OleParams::<Fp>::new(8, 27, 19).unwrap();
+            let mut generator = TripleGenerator::init(is_first, channel, &mut rng).unwrap();
+            let triples: Vec<Triple<Fp>> = generator
+                .generate_checked_with_params(n, &params, channel, &mut rng)
+                .unwrap();
+            triples
+        });
+
+        assert_eq!(first.len(), n);
+        for (t1, t2) in first.iter().zip(&second) {
+            let (a, b, c) = open(t1, t2);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            assert_eq!(c, ab);
+        }
+    }
+
+    #[test]
+    fn test_sacrifice_incorrect_triple() {
+        let (first, second) = run_both(move |is_first, channel| {
+            let mut rng = rand::thread_rng();
+            let mut generator = TripleGenerator::init(is_first, channel, &mut rng).unwrap();
+            let mut triples: Vec<Triple<Fp>> = generator.generate(10, channel, &mut rng).unwrap();
+            let sacrificed = triples.split_off(5);
+            if is_first {
+                triples[3].c.add_assign(&Fp::one());
+            }
+            generator.sacrifice(&triples, &sacrificed, channel, &mut rng)
+        });
+
+        match (first, second) {
+            (Err(OleError::TripleCheckFailed), Err(OleError::TripleCheckFailed)) => (),
+            (r1, r2) => panic!("expected TripleCheckFailed, got {:?} and {:?}", r1, r2),
+        }
+    }
+}
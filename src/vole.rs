@@ -0,0 +1,316 @@
+use crate::encoding;
+use crate::error::OleError;
+use crate::field::OleField;
+use crate::ole::{
+    ot_payload_len, read_elements, receiver_choices, receiver_open, sender_commit, write_elements,
+};
+use crate::params::VoleParams;
+use ocelot::ot::{KosReceiver, KosSender, Receiver as OTReceiver, Sender as OTSender};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::channel::AbstractChannel;
+
+// Vector OLE: the receiver learns y_i = a_i*x + b_i for a single scalar x.
+//
+// Compared to OLE the roles in the encoding are swapped: the sender encodes a as a
+// noisy Reed-Solomon codeword of a degree < alpha_order polynomial, and the receiver
+// evaluates x*c + r on it, where r is a codeword of a random degree < alpha_order
+// polynomial R. The sender learns the masked values at its alpha_order noiseless
+// positions only, decodes S = x*A + R and sends S + b at the alpha points, from which
+// the receiver removes R. As the degree doesn't grow, every instance gives alpha_order
+// VOLEs, twice the OLE batch, see VoleParams.
+//
+// The sender acts as OT receiver and the receiver as OT sender.
+//
+// Security is only against a passive receiver, hence the names PassiveVoleSender and
+// PassiveVoleReceiver. The sender checks the receiver's OT messages against its
+// commitment as in OLE, but unlike OLE neither party checks the polynomial identity
+// afterwards. A cheating sender gains nothing from that: whatever it sends, the
+// receiver's output is y = a'*x + b' for some a', b' it could have input instead. A
+// receiver that sends w not of the form x*c + r however learns more than a*x + b, e.g.
+// combinations of a at different x, and can learn which of the sender's positions are
+// noiseless. The OLE check doesn't carry over, as x is a single scalar that the
+// receiver can't open at a challenge without revealing it. Only use VOLE with a
+// receiver trusted to follow the protocol, otherwise use OLE with x repeated.
+
+pub struct PassiveVoleSender {
+    ot: KosReceiver,
+}
+
+impl PassiveVoleSender {
+    pub fn init<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Self, OleError> {
+        let ot = KosReceiver::init(channel, rng)?;
+        Ok(Self { ot: ot })
+    }
+
+    // runs VOLE with the default parameters of the field
+    pub fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        self.input_with_params(a, b, &VoleParams::default(), channel, rng)
+    }
+
+    // Inputs of any length are split into instances of params.batch_size() VOLEs each,
+    // the last instance is padded with random values. The receiver must use the same
+    // parameters.
+    pub fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        params: &VoleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        assert_eq!(a.len(), b.len());
+
+        let n = params.batch_size();
+        for (a_chunk, b_chunk) in a.chunks(n).zip(b.chunks(n)) {
+            self.input_instance(a_chunk, b_chunk, params, channel, rng)?;
+        }
+        return Ok(());
+    }
+
+    // runs a single VOLE instance on at most params.batch_size() inputs
+    fn input_instance<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        a: &[F],
+        b: &[F],
+        params: &VoleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<(), OleError> {
+        let mut com = [0u8; 32];
+        channel.read_bytes(&mut com)?;
+
+        let indices = encoding::pick_indices(params.alpha_order(), params.code_length(), rng);
+        let (choices, share_indices) = receiver_choices(&indices, params.code_length());
+        let vals = self.ot.receive(channel, &choices, rng)?;
+        let mut ot_payload = vec![0u8; ot_payload_len::<F>(choices.len())];
+        channel.read_bytes(&mut ot_payload)?;
+
+        let mask = receiver_open(
+            &com,
+            &vals,
+            &ot_payload,
+            &indices,
+            &share_indices,
+            params.ole_params(),
+        )?;
+
+        // noisy encoding of a
+        let mut a_poly = a.to_vec();
+        a_poly.resize_with(params.alpha_order(), || F::random(rng));
+        params.alpha_domain().fft_inverse(&mut a_poly);
+        let mut a_vals = params
+            .beta_domain()
+            .fft_pruned(&a_poly, params.code_length());
+        for i in share_indices.iter() {
+            a_vals[*i] = F::random(rng);
+        }
+
+        write_elements(channel, &a_vals)?;
+        channel.flush()?;
+
+        let mut ws: Vec<F> = read_elements(channel, params.code_length())?;
+        for (i, ti) in indices.iter().zip(&mask) {
+            ws[*i].sub_assign(ti);
+        }
+
+        let mut s_poly = encoding::decode_reed_solomon(&ws, &indices, params.ole_params());
+        s_poly.resize_with(params.alpha_order(), F::zero);
+        params.alpha_domain().fft(&mut s_poly);
+
+        s_poly.truncate(b.len());
+        for (s, bi) in s_poly.iter_mut().zip(b) {
+            s.add_assign(bi);
+        }
+        write_elements(channel, &s_poly)?;
+        channel.flush()?;
+
+        return Ok(());
+    }
+}
+
+pub struct PassiveVoleReceiver {
+    ot: KosSender,
+}
+
+impl PassiveVoleReceiver {
+    pub fn init<C: AbstractChannel, Crng: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Self, OleError> {
+        let ot = KosSender::init(channel, rng)?;
+        Ok(Self { ot: ot })
+    }
+
+    // runs VOLE with the default parameters of the field
+    pub fn input<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        x: &F,
+        n: usize,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError> {
+        self.input_with_params(x, n, &VoleParams::default(), channel, rng)
+    }
+
+    // Returns y_i = a_i*x + b_i for the n = a.len() inputs of the sender, split into
+    // instances of params.batch_size() VOLEs each.
+    pub fn input_with_params<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        x: &F,
+        n: usize,
+        params: &VoleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError> {
+        let mut result = Vec::with_capacity(n);
+        let mut remaining = n;
+        while remaining > 0 {
+            let len = std::cmp::min(remaining, params.batch_size());
+            let mut y = self.input_instance(x, len, params, channel, rng)?;
+            result.append(&mut y);
+            remaining -= len;
+        }
+        return Ok(result);
+    }
+
+    // runs a single VOLE instance with n <= params.batch_size() outputs
+    fn input_instance<F: OleField, C: AbstractChannel, Crng: CryptoRng + Rng>(
+        &mut self,
+        x: &F,
+        n: usize,
+        params: &VoleParams<F>,
+        channel: &mut C,
+        rng: &mut Crng,
+    ) -> Result<Vec<F>, OleError> {
+        let (com, ot_input, ot_payload, mask) = sender_commit(params.ole_params(), rng)?;
+        channel.write_bytes(&com)?;
+        channel.flush()?;
+
+        self.ot.send(channel, ot_input.as_slice(), rng)?;
+        channel.write_bytes(&ot_payload)?;
+        channel.flush()?;
+
+        let c: Vec<F> = read_elements(channel, params.code_length())?;
+
+        let r_poly: Vec<F> = (0..params.alpha_order()).map(|_| F::random(rng)).collect();
+        let r_vals = params
+            .beta_domain()
+            .fft_pruned(&r_poly, params.code_length());
+
+        let mut ws = c;
+        F::batch_scale(&mut ws, x);
+        F::batch_add(&mut ws, &r_vals);
+        F::batch_add(&mut ws, &mask);
+        write_elements(channel, &ws)?;
+        channel.flush()?;
+
+        let mut result: Vec<F> = read_elements(channel, n)?;
+        let mut r_alpha = r_poly;
+        params.alpha_domain().fft(&mut r_alpha);
+        F::batch_sub(&mut result, &r_alpha[..n]);
+
+        return Ok(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::tests::Fq2;
+    use crate::field::Fp;
+    use crate::field255::Fp255;
+    use crate::field64::Fp64;
+    use crate::test_utils::run_parties;
+    use rand;
+
+    #[test]
+    fn test_vole() {
+        check_vole::<Fp>();
+        check_vole::<Fp64>();
+        check_vole::<Fp255>();
+        check_vole::<Fq2>();
+    }
+
+    fn check_vole<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let n = F::A + 100;
+        let a: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let x = F::random(&mut rng);
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut volesender = PassiveVoleSender::init(&mut channel, &mut rng).unwrap();
+                volesender
+                    .input(&a_copy, &b_copy, &mut channel, &mut rng)
+                    .unwrap();
+            },
+            |mut channel| {
+                let mut volereceiver = PassiveVoleReceiver::init(&mut channel, &mut rng).unwrap();
+                volereceiver.input(&x, n, &mut channel, &mut rng).unwrap()
+            },
+        );
+
+        assert_eq!(result.len(), n);
+        for i in 0..n {
+            let mut expected = x;
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_vole_params() {
+        check_vole_params::<Fp>();
+        check_vole_params::<Fp64>();
+    }
+
+    fn check_vole_params<F: OleField>() {
+        let mut rng = rand::thread_rng();
+        let params = VoleParams::<F>::new(64, 729, 100).unwrap();
+        let n = 2 * params.batch_size() + 10;
+        let a: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let b: Vec<F> = (0..n).map(|_| F::random(&mut rng)).collect();
+        let x = F::random(&mut rng);
+
+        let a_copy = a.to_vec();
+        let b_copy = b.to_vec();
+        let params_copy = params.clone();
+        let (_, result) = run_parties(
+            move |mut channel| {
+                let mut rng = rand::thread_rng();
+                let mut volesender = PassiveVoleSender::init(&mut channel, &mut rng).unwrap();
+                volesender
+                    .input_with_params(&a_copy, &b_copy, &params_copy, &mut channel, &mut rng)
+                    .unwrap();
+            },
+            |mut channel| {
+                let mut volereceiver = PassiveVoleReceiver::init(&mut channel, &mut rng).unwrap();
+                volereceiver
+                    .input_with_params(&x, n, &params, &mut channel, &mut rng)
+                    .unwrap()
+            },
+        );
+
+        assert_eq!(result.len(), n);
+        for i in 0..n {
+            let mut expected = x;
+            expected.mul_assign(&a[i]);
+            expected.add_assign(&b[i]);
+            assert_eq!(result[i], expected);
+        }
+    }
+}